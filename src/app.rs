@@ -1,176 +1,1077 @@
-use egui::{vec2, Align2, Button, CentralPanel, Color32, Id, LayerId, Order, RichText, TextStyle};
-use std::sync::mpsc::{self, Receiver, Sender};
+use egui::{
+    vec2, Align2, Button, CentralPanel, Color32, Id, LayerId, Order, ProgressBar, RichText,
+    ScrollArea, SidePanel, TextStyle, TopBottomPanel, Ui,
+};
+use std::{
+    collections::BTreeSet,
+    path::Path,
+    sync::mpsc::{self, Receiver, Sender},
+    time::Duration,
+};
 
 use crate::{
     about_window::AboutWindow,
-    dav_table::render_table,
-    enums::{ChannelMessage, ThemeMode},
-    models::TableStruct,
-    top_bar::render_top_bar,
-    utils::{handle_keyboard_shortcuts, thrd_read_file, thrd_select_file},
+    archive_picker::ArchivePicker,
+    cli::LaunchOptions,
+    dav_table::render_data_tab,
+    enums::{ChannelMessage, FileFormat, JobKind, TableTab, ThemeMode},
+    error_panel::render_error_panel,
+    errors::DavError,
+    export_dialog::ExportDialog,
+    import_dialog::ImportDialog,
+    jobs::{JobId, JobManager},
+    models::{DataTab, Document, ErrorReport, ImportOptions, LoadProgress, LoadState, TableStruct},
+    read_file::{archive_entries, detect_format, has_import_options, is_extracted, remove_cached},
+    recent_files::render_recent_files,
+    settings::{RecentFile, SessionFile, Settings},
+    sql_console::SqlConsole,
+    status_bar::render_status_bar,
+    top_bar::{render_document_tabs, render_top_bar},
+    utils::{
+        handle_keyboard_shortcuts, thrd_apply_view, thrd_export_file, thrd_extract_entry,
+        thrd_preview_file, thrd_read_file, thrd_run_query, thrd_run_sqlite_query, thrd_select_file,
+    },
 };
 
 #[derive(Debug)]
 pub struct AppModel {
-    pub file_path: Option<String>,
-    pub table_struct: Option<TableStruct>,
+    pub documents: Vec<Document>,
+    pub active_document: Option<usize>,
+    next_document_id: usize,
+
+    sql_console: SqlConsole,
+    pub is_sql_console_open: bool,
+    query_job: Option<JobId>,
+
+    pub jobs: JobManager,
+    pub errors: Vec<ErrorReport>,
 
     pub selected_theme: ThemeMode,
 
     about_window: Box<AboutWindow>,
     pub is_about_window_open: bool,
-
-    error: Option<String>,
+    import_dialog: Option<ImportDialog>,
+    archive_picker: Option<ArchivePicker>,
+    export_dialog: Option<ExportDialog>,
+    /// Command line options, waiting for their files to open
+    launch: LaunchOptions,
+    settings: Settings,
+    /// Tabs of the previous session, waiting for their file to open
+    session: Vec<SessionFile>,
 
     pub tx: Sender<ChannelMessage>,
     rx: Receiver<ChannelMessage>,
 }
 
 impl AppModel {
-    pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
+    pub fn new(
+        cc: &eframe::CreationContext<'_>,
+        settings: Settings,
+        launch: LaunchOptions,
+        theme: Option<ThemeMode>,
+    ) -> Self {
         let (tx, rx) = mpsc::channel::<ChannelMessage>();
 
+        // ! Reopen the previous session, then the files given on the command
+        // ! line, like picked ones
+        let session = settings
+            .session
+            .iter()
+            .filter(|file| Path::new(&file.file_path).exists())
+            .cloned()
+            .collect::<Vec<SessionFile>>();
+        for file in session.iter() {
+            tx.send(ChannelMessage::OpenFile(file.file_path.clone()))
+                .unwrap();
+        }
+        for file_path in launch.files.iter() {
+            tx.send(ChannelMessage::OpenFile(file_path.clone()))
+                .unwrap();
+        }
+
+        let theme = theme.unwrap_or_else(|| settings.theme.clone());
+        theme.apply(&cc.egui_ctx);
+        if let Some(zoom) = settings.zoom {
+            let native = cc.integration_info.native_pixels_per_point.unwrap_or(1.);
+            cc.egui_ctx.set_pixels_per_point(native * zoom);
+        }
+
         let mut fonts = egui::FontDefinitions::default();
         egui_phosphor::add_to_fonts(&mut fonts, egui_phosphor::Variant::Regular);
 
         cc.egui_ctx.set_fonts(fonts);
 
         AppModel {
-            file_path: None,
-            table_struct: None,
+            documents: vec![],
+            active_document: None,
+            next_document_id: 0,
+
+            sql_console: SqlConsole::default(),
+            is_sql_console_open: false,
+            query_job: None,
+
+            jobs: JobManager::default(),
+            errors: vec![],
 
-            selected_theme: ThemeMode::Dark,
+            selected_theme: theme,
 
             about_window: Box::<AboutWindow>::default(),
             is_about_window_open: false,
-
-            error: None,
+            import_dialog: None,
+            archive_picker: None,
+            export_dialog: None,
+            launch,
+            settings,
+            session,
 
             tx,
             rx,
         }
     }
+
+    /// Opens a file in a new tab, or focuses its tab when already open.
+    pub fn open_file(&mut self, file_path: String) {
+        let restored = self
+            .session
+            .iter()
+            .position(|file| file.file_path == file_path)
+            .map(|idx| self.session.remove(idx));
+        if let Some(doc) = self.documents.iter().find(|d| d.file_path == file_path) {
+            // ! Further tables of a database left open in the previous session
+            match restored.filter(|_| doc.is_database) {
+                Some(file) => self.restore_table(file),
+                None => self.active_document = Some(doc.id),
+            }
+            return;
+        }
+
+        // ! Copies extracted from an archive are temporary, the archive is
+        // ! listed instead
+        if !is_extracted(&file_path) && Path::new(&file_path).exists() {
+            self.settings.push_recent(&file_path);
+            self.settings.save();
+        }
+
+        // ! An archive is not a table, the user picks one of its files
+        let format = detect_format(&file_path).ok();
+        if format == Some(FileFormat::Archive) {
+            match archive_entries(&file_path) {
+                Ok(entries) if entries.len() == 1 => {
+                    thrd_extract_entry(self.tx.clone(), file_path, entries[0].clone());
+                }
+                Ok(entries) => {
+                    self.archive_picker = Some(ArchivePicker::new(file_path, entries));
+                }
+                Err(err) => self.report_file_error(file_path, err),
+            }
+            return;
+        }
+
+        let id = self.next_document_id;
+        self.next_document_id += 1;
+
+        let mut doc = Document::new(id, file_path.clone());
+        doc.is_database = format == Some(FileFormat::Sqlite);
+        doc.active_sheet = self.settings.last_sheets.get(&file_path).cloned();
+        if let Some(options) = self.settings.import_options.get(&file_path) {
+            doc.import_options = options.clone();
+        }
+
+        // ! Restored tabs keep their sheet and scroll, only the one that was
+        // ! shown gets the focus
+        let mut focus = true;
+        if let Some(file) = restored {
+            doc.active_sheet = file.sheet.or(doc.active_sheet);
+            doc.restore_scroll = Some(file.scroll);
+            focus = self.active_document.is_none()
+                || self.settings.active_file.as_ref() == Some(&file_path);
+        }
+
+        // ! Command line options only apply to the files given with them
+        if let Some(idx) = self.launch.files.iter().position(|f| *f == file_path) {
+            self.launch.files.remove(idx);
+            if let Some(sheet) = &self.launch.sheet {
+                doc.active_sheet = Some(sheet.clone());
+            }
+            // ! Without `--delimiter` the saved options of the file stay
+            if let Some(delimiter) = self.launch.delimiter {
+                doc.import_options.csv.delimiter = Some(delimiter);
+            }
+        }
+
+        self.push_document(doc, focus);
+    }
+
+    /// Opens a table of a database in its own tab, or focuses the tab already
+    /// showing it.
+    pub fn open_table(&mut self, file_path: String, table: String) {
+        if let Some(doc) = self
+            .documents
+            .iter()
+            .find(|d| d.file_path == file_path && d.active_sheet.as_ref() == Some(&table))
+        {
+            self.active_document = Some(doc.id);
+            return;
+        }
+
+        let doc = self.table_document(file_path.clone(), table.clone());
+        self.settings.last_sheets.insert(file_path, table);
+        self.settings.save();
+        self.push_document(doc, true);
+    }
+
+    fn restore_table(&mut self, file: SessionFile) {
+        let Some(table) = file.sheet else {
+            return;
+        };
+        let focus = self.settings.active_file.as_ref() == Some(&file.file_path);
+        let mut doc = self.table_document(file.file_path, table);
+        doc.restore_scroll = Some(file.scroll);
+        self.push_document(doc, focus);
+    }
+
+    /// New tab for a table of a database, listing the tables already known.
+    fn table_document(&mut self, file_path: String, table: String) -> Document {
+        let id = self.next_document_id;
+        self.next_document_id += 1;
+
+        let mut doc = Document::new(id, file_path);
+        doc.is_database = true;
+        doc.sheets = self
+            .documents
+            .iter()
+            .find(|d| d.file_path == doc.file_path)
+            .map(|d| d.sheets.clone())
+            .unwrap_or_default();
+        doc.active_sheet = Some(table);
+        doc
+    }
+
+    fn push_document(&mut self, doc: Document, focus: bool) {
+        let id = doc.id;
+        self.documents.push(doc);
+        if focus {
+            self.active_document = Some(id);
+        }
+        self.start_load(id);
+    }
+
+    pub fn recent_files(&self) -> Vec<RecentFile> {
+        self.settings.recent_files()
+    }
+
+    pub fn toggle_recent_pin(&mut self, file_path: &str) {
+        self.settings.toggle_pin(file_path);
+        self.settings.save();
+    }
+
+    pub fn remove_recent(&mut self, file_path: &str) {
+        self.settings.remove_recent(file_path);
+        self.settings.save();
+    }
+
+    /// Reads the document file (its active sheet for a workbook) in the
+    /// background.
+    fn start_load(&mut self, id: usize) {
+        let Some(doc) = self.documents.iter_mut().find(|d| d.id == id) else {
+            return;
+        };
+
+        let label = match &doc.active_sheet {
+            Some(sheet) => format!("Loading {} ({})", doc.title(), sheet),
+            None => format!("Loading {}", doc.title()),
+        };
+        let (job_id, cancel) = self.jobs.start(JobKind::Load, id, TableTab::Data, label);
+        doc.loading = Some(LoadState {
+            job_id,
+            progress: LoadProgress::default(),
+        });
+
+        thrd_read_file(
+            self.tx.clone(),
+            job_id,
+            doc.file_path.clone(),
+            doc.active_sheet.clone(),
+            doc.import_options.clone(),
+            cancel,
+        );
+    }
+
+    /// Opens the import options of the active document, for text tables and
+    /// workbooks.
+    pub fn open_import_dialog(&mut self) {
+        let Some(doc) = self.active_document() else {
+            return;
+        };
+        if !has_import_options(&doc.file_path) {
+            return;
+        }
+
+        self.import_dialog = Some(ImportDialog::new(
+            doc.id,
+            doc.file_path.clone(),
+            doc.active_sheet.clone(),
+            doc.import_options.clone(),
+        ));
+    }
+
+    /// Reloads a document with new import options, dropping the sheets read
+    /// with the previous ones.
+    fn reimport(&mut self, id: usize, options: ImportOptions) {
+        let Some(doc) = self.documents.iter_mut().find(|d| d.id == id) else {
+            return;
+        };
+        if let Some(loading) = doc.loading.take() {
+            self.jobs.cancel(loading.job_id);
+        }
+        self.settings
+            .import_options
+            .insert(doc.file_path.clone(), options.clone());
+        doc.import_options = options;
+        doc.data = None;
+        doc.query_result = None;
+        doc.active_tab = TableTab::Data;
+        doc.sheet_cache.clear();
+
+        self.start_load(id);
+    }
+
+    fn render_import_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.import_dialog else {
+            return;
+        };
+
+        // ! Refresh the preview when an option changes, superseding the
+        // ! previous one
+        if dialog.needs_preview() {
+            if let Some(job_id) = dialog.preview_job.take() {
+                self.jobs.cancel(job_id);
+            }
+            let (job_id, _) = self.jobs.start(
+                JobKind::Preview,
+                dialog.doc_id,
+                TableTab::Data,
+                "Previewing import",
+            );
+            dialog.preview_job = Some(job_id);
+            dialog.previewed = Some(dialog.options.clone());
+            thrd_preview_file(
+                self.tx.clone(),
+                job_id,
+                dialog.file_path.clone(),
+                dialog.sheet.clone(),
+                dialog.options.clone(),
+            );
+        }
+
+        let mut open = true;
+        let confirmed = dialog.show(ctx, &mut open);
+        if open {
+            return;
+        }
+
+        if let Some(job_id) = dialog.preview_job {
+            self.jobs.cancel(job_id);
+        }
+        let (id, options) = (dialog.doc_id, dialog.options.clone());
+        self.import_dialog = None;
+        if confirmed {
+            self.reimport(id, options);
+        }
+    }
+
+    /// Offers to export the tab shown in the active document, as filtered and
+    /// sorted.
+    pub fn open_export_dialog(&mut self) {
+        let Some(doc) = self.active_document() else {
+            return;
+        };
+        let Some(data_tab) = doc.tab(doc.active_tab) else {
+            return;
+        };
+
+        let file_stem = doc
+            .title()
+            .split('.')
+            .next()
+            .unwrap_or("export")
+            .to_string();
+        self.export_dialog = Some(ExportDialog::new(
+            doc.id,
+            doc.active_tab,
+            file_stem,
+            &data_tab.table.columns,
+        ));
+    }
+
+    fn render_export_dialog(&mut self, ctx: &egui::Context) {
+        let Some(dialog) = &mut self.export_dialog else {
+            return;
+        };
+
+        let mut open = true;
+        let confirmed = dialog.show(ctx, &mut open);
+        if open {
+            return;
+        }
+
+        let dialog = self.export_dialog.take().unwrap();
+        if !confirmed {
+            return;
+        }
+        let Some(doc) = self.documents.iter_mut().find(|d| d.id == dialog.doc_id) else {
+            return;
+        };
+        let label = format!("Exporting {} to {}", doc.title(), dialog.format.label());
+        let Some(data_tab) = doc.tab_mut(dialog.tab) else {
+            return;
+        };
+
+        match data_tab.table.df.select(dialog.selected_columns()) {
+            Ok(df) => {
+                let (job_id, cancel) =
+                    self.jobs
+                        .start(JobKind::Export, dialog.doc_id, dialog.tab, label);
+                thrd_export_file(
+                    self.tx.clone(),
+                    job_id,
+                    df,
+                    dialog.format,
+                    dialog.codec,
+                    dialog.file_stem,
+                    cancel,
+                );
+            }
+            Err(err) => self.report_error(dialog.doc_id, err.into()),
+        }
+    }
+
+    fn render_archive_picker(&mut self, ctx: &egui::Context) {
+        let Some(picker) = &mut self.archive_picker else {
+            return;
+        };
+
+        let mut open = true;
+        let picked = picker.show(ctx, &mut open);
+        if let Some(entry) = picked {
+            thrd_extract_entry(self.tx.clone(), picker.file_path.clone(), entry);
+        }
+        if !open {
+            self.archive_picker = None;
+        }
+    }
+
+    /// Shows another sheet of a workbook, reading it on first selection.
+    pub fn select_sheet(&mut self, id: usize, sheet: String) {
+        let Some(doc) = self.documents.iter_mut().find(|d| d.id == id) else {
+            return;
+        };
+        if doc.active_sheet.as_ref() == Some(&sheet) {
+            return;
+        }
+
+        // ! Put the current sheet aside, unless it was only partly read
+        let previous = doc.active_sheet.replace(sheet.clone());
+        match doc.loading.take() {
+            Some(loading) => self.jobs.cancel(loading.job_id),
+            None => {
+                if let Some((previous, data)) = previous.zip(doc.data.take()) {
+                    doc.sheet_cache.insert(previous, data);
+                }
+            }
+        }
+        doc.data = doc.sheet_cache.remove(&sheet);
+        doc.active_tab = TableTab::Data;
+
+        self.settings
+            .last_sheets
+            .insert(doc.file_path.clone(), sheet);
+        self.settings.save();
+
+        if self.document_mut(id).map_or(false, |d| d.data.is_none()) {
+            self.start_load(id);
+        }
+    }
+
+    pub fn close_document(&mut self, id: usize) {
+        let Some(idx) = self.documents.iter().position(|d| d.id == id) else {
+            return;
+        };
+        let doc = self.documents.remove(idx);
+        self.jobs.cancel_document(id);
+
+        // ! Another tab of the same database may still read the copy
+        if !self.documents.iter().any(|d| d.file_path == doc.file_path) {
+            remove_cached(&doc.file_path);
+        }
+
+        // ! Focus the neighbour tab
+        if self.active_document == Some(id) {
+            self.active_document = self
+                .documents
+                .get(idx)
+                .or_else(|| self.documents.last())
+                .map(|d| d.id);
+        }
+    }
+
+    pub fn close_active_document(&mut self) {
+        if let Some(id) = self.active_document {
+            self.close_document(id);
+        }
+    }
+
+    pub fn active_document(&self) -> Option<&Document> {
+        let id = self.active_document?;
+        self.documents.iter().find(|d| d.id == id)
+    }
+
+    fn document_mut(&mut self, id: usize) -> Option<&mut Document> {
+        self.documents.iter_mut().find(|d| d.id == id)
+    }
+
+    fn handle_message(&mut self, message: ChannelMessage) {
+        match message {
+            ChannelMessage::OpenFile(file_path) => {
+                self.open_file(file_path);
+            }
+            ChannelMessage::FileError(file_path, err) => {
+                self.report_file_error(file_path, err);
+            }
+            ChannelMessage::SheetNames(job_id, sheets) => {
+                let doc_id = self.jobs.get(job_id).map(|j| j.doc_id);
+                if let Some(doc) = doc_id.and_then(|id| self.document_mut(id)) {
+                    if !doc
+                        .active_sheet
+                        .as_ref()
+                        .map_or(false, |s| sheets.contains(s))
+                    {
+                        doc.active_sheet = sheets.first().cloned();
+                    }
+                    doc.sheets = sheets;
+                }
+            }
+            ChannelMessage::LoadProgress(job_id, progress) => {
+                if let Some(loading) = self.loading_mut(job_id) {
+                    loading.progress = progress;
+                }
+            }
+            ChannelMessage::LoadBatch(job_id, df) => {
+                let doc_id = self.jobs.get(job_id).map(|j| j.doc_id);
+                if let Some(doc) = doc_id.and_then(|id| self.document_mut(id)) {
+                    match &mut doc.data {
+                        Some(data) => data.table = TableStruct::new(df),
+                        None => doc.data = Some(DataTab::new(TableStruct::new(df))),
+                    }
+                }
+            }
+            ChannelMessage::ReadFile(job_id, table_struct) => {
+                if let Some(doc) = self.finish_job(job_id) {
+                    doc.loading = None;
+                    doc.data = Some(DataTab::new(table_struct));
+                }
+            }
+            ChannelMessage::ReadFileError(job_id, err) => {
+                let Some(doc) = self.finish_job(job_id) else {
+                    return;
+                };
+                doc.loading = None;
+                let (id, has_data) = (doc.id, doc.data.is_some() || !doc.sheets.is_empty());
+                self.report_error(id, err);
+
+                // ! Keep the rows read before the error, or the other sheets
+                if !has_data {
+                    self.close_document(id);
+                }
+            }
+            ChannelMessage::ViewApplied(job_id, df) => {
+                let tab = self.jobs.get(job_id).map(|j| j.tab);
+                if let Some(data_tab) = self
+                    .finish_job(job_id)
+                    .zip(tab)
+                    .and_then(|(doc, tab)| doc.tab_mut(tab))
+                {
+                    data_tab.view_job = None;
+                    data_tab.table.set_view(df);
+                    data_tab.selected_row = None;
+                }
+            }
+            ChannelMessage::ViewError(job_id, err) => {
+                if let Some(id) = self.finish_job(job_id).map(|doc| doc.id) {
+                    self.report_error(id, err);
+                }
+            }
+            ChannelMessage::PreviewResult(job_id, df) => {
+                if let Some(dialog) = self.finish_preview(job_id) {
+                    dialog.preview = Some(TableStruct::new(df));
+                    dialog.error = None;
+                }
+            }
+            ChannelMessage::PreviewError(job_id, err) => {
+                if let Some(dialog) = self.finish_preview(job_id) {
+                    dialog.preview = None;
+                    dialog.error = Some(err);
+                }
+            }
+            ChannelMessage::ExportProgress(job_id, fraction) => {
+                self.jobs.set_progress(job_id, fraction);
+            }
+            ChannelMessage::ExportFinished(job_id, file_path) => {
+                if self.jobs.finish(job_id).is_some() {
+                    println!("[*] Exported to {}", file_path);
+                }
+            }
+            ChannelMessage::ExportError(job_id, err) => {
+                if let Some(job) = self.jobs.finish(job_id) {
+                    if err != DavError::Cancelled {
+                        self.report_error(job.doc_id, err);
+                    }
+                }
+            }
+            ChannelMessage::QueryResult(job_id, table_struct) => {
+                if self.query_job == Some(job_id) {
+                    self.query_job = None;
+                    self.sql_console.is_running = false;
+                }
+                if let Some(doc) = self.finish_job(job_id) {
+                    doc.query_result = Some(DataTab::new(table_struct));
+                    doc.active_tab = TableTab::Query;
+                }
+            }
+            ChannelMessage::QueryError(job_id, err) => {
+                if self.jobs.finish(job_id).is_some() && self.query_job == Some(job_id) {
+                    self.query_job = None;
+                    self.sql_console.is_running = false;
+                    self.sql_console.error = Some(err.to_string());
+                }
+            }
+        }
+    }
+
+    fn report_error(&mut self, doc_id: usize, error: DavError) {
+        let Some(doc) = self.documents.iter().find(|d| d.id == doc_id) else {
+            return;
+        };
+        self.report_file_error(doc.file_path.clone(), error);
+    }
+
+    fn report_file_error(&mut self, file_path: String, error: DavError) {
+        println!("[!] {}: {}", file_path, error);
+        self.errors.push(ErrorReport { file_path, error });
+    }
+
+    /// Document a finished job worked for, `None` when the result is stale.
+    fn finish_job(&mut self, job_id: JobId) -> Option<&mut Document> {
+        let job = self.jobs.finish(job_id)?;
+        self.document_mut(job.doc_id)
+    }
+
+    fn finish_preview(&mut self, job_id: JobId) -> Option<&mut ImportDialog> {
+        self.jobs.finish(job_id)?;
+        let dialog = self.import_dialog.as_mut()?;
+        if dialog.preview_job != Some(job_id) {
+            return None;
+        }
+        dialog.preview_job = None;
+        Some(dialog)
+    }
+
+    fn loading_mut(&mut self, job_id: JobId) -> Option<&mut LoadState> {
+        let doc_id = self.jobs.get(job_id)?.doc_id;
+        self.document_mut(doc_id)?
+            .loading
+            .as_mut()
+            .filter(|l| l.job_id == job_id)
+    }
 }
 
 impl eframe::App for AppModel {
+    fn on_exit(&mut self, _gl: Option<&eframe::glow::Context>) {
+        self.settings.theme = self.selected_theme.clone();
+        self.settings.session = self
+            .documents
+            .iter()
+            .map(|doc| SessionFile {
+                file_path: doc.file_path.clone(),
+                sheet: doc.active_sheet.clone(),
+                // ! A tab still loading keeps the offset it was restored with
+                scroll: doc
+                    .restore_scroll
+                    .or_else(|| doc.data.as_ref().map(|data| data.scroll.offset))
+                    .unwrap_or_default(),
+            })
+            .collect();
+        self.settings.active_file = self.active_document().map(|doc| doc.file_path.clone());
+        self.settings.save();
+
+        // ! Decompressed and extracted copies are made again when needed
+        let file_paths = self
+            .documents
+            .iter()
+            .map(|doc| doc.file_path.as_str())
+            .collect::<BTreeSet<&str>>();
+        for file_path in file_paths {
+            remove_cached(file_path);
+        }
+    }
+
     fn update(&mut self, ctx: &egui::Context, frame: &mut eframe::Frame) {
+        // * Keep polling while jobs run
+        if !self.jobs.jobs().is_empty() {
+            ctx.request_repaint_after(Duration::from_millis(100));
+        }
+
+        // * Remember the zoom and window size for the next launch
+        let window_size = frame.info().window_info.size;
+        // ! Relative to the display, so another screen keeps its own scale
+        let zoom = ctx.pixels_per_point() / frame.info().native_pixels_per_point.unwrap_or(1.);
+        self.settings.zoom = ((zoom - 1.).abs() > 0.01).then_some(zoom);
+        self.settings.window_size = Some([window_size.x, window_size.y]);
+
+        // * Drop the state of jobs cancelled from the status bar
+        for doc in self.documents.iter_mut() {
+            if let Some(loading) = &doc.loading {
+                if !self.jobs.is_running(loading.job_id) {
+                    doc.loading = None;
+                }
+            }
+            for tab in [TableTab::Data, TableTab::Query] {
+                if let Some(data_tab) = doc.tab_mut(tab) {
+                    if let Some(job_id) = data_tab.view_job {
+                        if !self.jobs.is_running(job_id) {
+                            data_tab.view_job = None;
+                        }
+                    }
+                }
+            }
+        }
+        if let Some(job_id) = self.query_job {
+            if !self.jobs.is_running(job_id) {
+                self.query_job = None;
+                self.sql_console.is_running = false;
+            }
+        }
+        if let Some(dialog) = &mut self.import_dialog {
+            if let Some(job_id) = dialog.preview_job {
+                if !self.jobs.is_running(job_id) {
+                    dialog.preview_job = None;
+                }
+            }
+        }
+
         // * Handle windows
         if self.is_about_window_open {
             self.about_window.show(ctx, &mut self.is_about_window_open);
         }
+        self.render_import_dialog(ctx);
+        self.render_archive_picker(ctx);
+        self.render_export_dialog(ctx);
 
         // * Top bar
         render_top_bar(self, ctx, frame);
 
+        // * Errors
+        if !self.errors.is_empty() {
+            render_error_panel(self, ctx);
+        }
+
+        // * Status bar
+        render_status_bar(self, ctx);
+
+        // * Document tabs
+        if !self.documents.is_empty() {
+            render_document_tabs(self, ctx);
+        }
+
+        // * SQL console
+        if self.is_sql_console_open {
+            let target = self.active_document().and_then(|doc| {
+                doc.data.as_ref().map(|data| {
+                    (
+                        doc.id,
+                        doc.is_database.then(|| doc.file_path.clone()),
+                        data.table.source.clone(),
+                    )
+                })
+            });
+            if let Some((id, database, source)) = target {
+                if let Some(query) = self.sql_console.show(ctx, database.is_some()) {
+                    if let Some(job_id) = self.query_job.take() {
+                        self.jobs.cancel(job_id);
+                    }
+                    let (job_id, _) =
+                        self.jobs
+                            .start(JobKind::Query, id, TableTab::Query, "Running SQL query");
+                    self.query_job = Some(job_id);
+                    match database {
+                        // ! Runs in SQLite, every table of the database can be queried
+                        Some(file_path) => {
+                            thrd_run_sqlite_query(self.tx.clone(), job_id, file_path, query)
+                        }
+                        None => thrd_run_query(self.tx.clone(), job_id, source, query),
+                    }
+                }
+            }
+        }
+
         // * Central panel
         CentralPanel::default().show(ctx, |ui| {
             // ! Handle shortcuts
             handle_keyboard_shortcuts(ui, self, frame);
 
             // ! Check for messages from other threads
-            if let Ok(rx_type) = self.rx.try_recv() {
-                match rx_type {
-                    ChannelMessage::OpenFile(file_path) => {
-                        self.file_path = Some(file_path.clone());
-                        thrd_read_file(self.tx.clone(), file_path);
-                    }
-                    ChannelMessage::ReadFile(table_struct) => {
-                        self.table_struct = Some(table_struct);
+            while let Ok(rx_type) = self.rx.try_recv() {
+                self.handle_message(rx_type);
+            }
+
+            // ! Context & Rect
+            let screen_rect = ui.max_rect();
+
+            // ! Collect dropped files, each one opens in its own tab
+            let dropped_files = ui.ctx().input(|i| {
+                i.raw
+                    .dropped_files
+                    .iter()
+                    .filter_map(|f| f.path.as_ref())
+                    .filter_map(|p| p.to_str())
+                    .map(|p| p.replace('\\', "/"))
+                    .collect::<Vec<String>>()
+            });
+            for file_path in dropped_files {
+                self.open_file(file_path);
+            }
+
+            if self.active_document.is_none() {
+                // ! Drop/Open file, the whole screen reacts to a double click
+                let background =
+                    ui.interact(screen_rect, Id::new("drop_zone"), egui::Sense::click());
+                if background.double_clicked() {
+                    thrd_select_file(self.tx.clone());
+                }
+
+                ui.vertical_centered(|ui| {
+                    ui.add_space(ui.available_height() / 4.);
+                    let open_btn = Button::new(
+                        RichText::new(
+                            "Double click anywhere to open a file\n\t\t\t\tor drop it here",
+                        )
+                        .color(Color32::from_rgb(255, 255, 255))
+                        .text_style(TextStyle::Button),
+                    )
+                    .frame(false);
+
+                    let btn_response = ui.add_sized(vec2(200., 50.), open_btn);
+
+                    if btn_response.double_clicked() {
+                        thrd_select_file(self.tx.clone());
                     }
-                    ChannelMessage::ReadFileError(err) => {
-                        self.error = Some(err);
+
+                    // ! Recent files
+                    if !self.recent_files().is_empty() {
+                        ui.add_space(20.);
+                        ui.label(RichText::new("Recent files").strong());
+                        ui.add_space(5.);
+                        ScrollArea::vertical()
+                            .max_height(ui.available_height() / 2.)
+                            .show(ui, |ui| {
+                                render_recent_files(ui, self);
+                            });
                     }
-                }
+                });
             }
 
-            // ! Error message
-            if let Some(err) = &self.error {
-                ui.centered_and_justified(|ui| {
-                    ui.label(err);
+            // ! Preview hovering files:
+            let ctx = ui.ctx();
+            if !ctx.input(|i| i.raw.hovered_files.is_empty()) {
+                let painter =
+                    ctx.layer_painter(LayerId::new(Order::Foreground, Id::new("file_drop_target")));
+                painter.rect_filled(
+                    screen_rect,
+                    7.5,
+                    Color32::from_rgba_premultiplied(0, 0, 0, 128),
+                );
+                painter.text(
+                    screen_rect.center(),
+                    Align2::CENTER_CENTER,
+                    "Drop it here",
+                    TextStyle::Heading.resolve(&ctx.style()),
+                    ui.visuals().text_color(),
+                );
+            }
+
+            // ! Active document
+            let tx = self.tx.clone();
+            let active_document = self.active_document;
+            let mut picked_sheet = None;
+            if let Some(doc) = self
+                .documents
+                .iter_mut()
+                .find(|d| Some(d.id) == active_document)
+            {
+                ui.push_id(doc.id, |ui| {
+                    picked_sheet = render_document(ui, doc, &mut self.jobs, &tx);
                 });
-                return;
-            }
-
-            if self.file_path.is_none() {
-                // ! Drop/Open file
-                let response = ui
-                    .allocate_ui(ui.available_size_before_wrap(), |ui| {
-                        ui.centered_and_justified(|ui| {
-                            let open_btn = Button::new(
-                                RichText::new(
-                                    "Double click anywhere to open a file\n\t\t\t\tor drop it here",
-                                )
-                                .color(Color32::from_rgb(255, 255, 255))
-                                .text_style(TextStyle::Button),
-                            )
-                            .frame(false);
-
-                            let btn_response = ui.add_sized(vec2(200., 50.), open_btn);
-
-                            if btn_response.double_clicked() {
-                                thrd_select_file(self.tx.clone());
-                            }
+            }
+            if let Some((id, sheet)) = active_document.zip(picked_sheet) {
+                // ! Each table of a database gets a tab of its own
+                match self.active_document().filter(|doc| doc.is_database) {
+                    Some(doc) => self.open_table(doc.file_path.clone(), sheet),
+                    None => self.select_sheet(id, sheet),
+                }
+            }
+        });
+    }
+}
+
+/// Renders a document and returns the sheet picked in its sheet bar, or the
+/// table picked in its side panel, if any.
+fn render_document(
+    ui: &mut Ui,
+    doc: &mut Document,
+    jobs: &mut JobManager,
+    tx: &Sender<ChannelMessage>,
+) -> Option<String> {
+    // ! Load progress
+    if let Some(loading) = &doc.loading {
+        let progress = loading.progress;
+        let mut cancelled = false;
+
+        ui.horizontal(|ui| {
+            let text = if progress.total_bytes > 0 {
+                format!(
+                    "Loading... {} rows ({:.1} / {:.1} MB)",
+                    progress.rows,
+                    progress.bytes as f64 / 1_048_576.,
+                    progress.total_bytes as f64 / 1_048_576.
+                )
+            } else {
+                format!("Loading... {} rows", progress.rows)
+            };
+
+            if ui
+                .button(format!("{} Cancel", egui_phosphor::regular::X))
+                .clicked()
+            {
+                cancelled = true;
+            }
+
+            let bar = ProgressBar::new(progress.fraction.unwrap_or(0.))
+                .text(text)
+                .animate(progress.fraction.is_none());
+            ui.add(bar);
+        });
+
+        // ! Keep what was read so far
+        if cancelled {
+            jobs.cancel(loading.job_id);
+            doc.loading = None;
+        }
+    }
 
-                            // if btn_response.hovered() {
-                            //     ui.output_mut(|opt| opt.cursor_icon = CursorIcon::PointingHand);
-                            // }
-                        });
-                    })
-                    .response;
-
-                // ui.painter()
-                //     .rect_stroke(response.rect, 7.5, (1.0, ui.visuals().text_color()));
-
-                // ! Context & Rect
-                let ctx = ui.ctx();
-                let screen_rect = response.rect;
-
-                // ! Preview hovering files:
-                if !ctx.input(|i| i.raw.hovered_files.is_empty()) {
-                    let painter = ctx.layer_painter(LayerId::new(
-                        Order::Foreground,
-                        Id::new("file_drop_target"),
-                    ));
-                    painter.rect_filled(
-                        screen_rect,
-                        7.5,
-                        Color32::from_rgba_premultiplied(0, 0, 0, 128),
-                    );
-                    painter.text(
-                        screen_rect.center(),
-                        Align2::CENTER_CENTER,
-                        "Drop it here",
-                        TextStyle::Heading.resolve(&ctx.style()),
-                        ui.visuals().text_color(),
-                    );
-                }
-
-                // ! Collect dropped files:
-                ctx.input(|i| {
-                    if !i.raw.dropped_files.is_empty() {
-                        if let Some(pos) = i.pointer.hover_pos() {
-                            if screen_rect.contains(pos) {
-                                let first_path = i.raw.dropped_files[0]
-                                    .clone()
-                                    .path
-                                    .unwrap()
-                                    .to_str()
-                                    .unwrap()
-                                    .replace('\\', "/");
-                                self.file_path = Some(first_path.clone());
-                                thrd_read_file(self.tx.clone(), first_path);
+    // ! Tables and views of a database, listed on the side
+    let mut picked_sheet = None;
+    if doc.is_database {
+        SidePanel::left("database_tables")
+            .resizable(true)
+            .default_width(180.)
+            .show_inside(ui, |ui| {
+                ui.label(RichText::new("Tables and views").strong());
+                ui.separator();
+                ScrollArea::vertical()
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for table in doc.sheets.iter() {
+                            let is_active = doc.active_sheet.as_ref() == Some(table);
+                            let label = format!("{} {}", egui_phosphor::regular::TABLE, table);
+                            if ui.selectable_label(is_active, label).clicked() && !is_active {
+                                picked_sheet = Some(table.clone());
                             }
                         }
+                    });
+                ui.separator();
+                ui.label(RichText::new("Run a custom SELECT from the SQL console").weak());
+            });
+    }
+
+    // ! Sheets of a workbook, along the bottom like a spreadsheet
+    if !doc.sheets.is_empty() && !doc.is_database {
+        TopBottomPanel::bottom("sheet_bar").show_inside(ui, |ui| {
+            ScrollArea::horizontal().show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    for sheet in doc.sheets.iter() {
+                        let is_active = doc.active_sheet.as_ref() == Some(sheet);
+                        if ui.selectable_label(is_active, sheet).clicked() && !is_active {
+                            picked_sheet = Some(sheet.clone());
+                        }
                     }
                 });
+            });
+        });
+    }
+
+    // ! Still waiting for the first rows
+    if doc.data.is_none() {
+        ui.centered_and_justified(|ui| {
+            if doc.loading.is_some() {
+                ui.spinner();
+            } else {
+                ui.label("No data loaded");
             }
+        });
+        return picked_sheet;
+    }
 
-            if let Some(table_struct) = &self.table_struct {
-                // ! Render table
-                ui.group(|ui| {
-                    render_table(ui, table_struct);
-                });
+    // ! Data/Query result tabs
+    if doc.query_result.is_some() {
+        ui.horizontal(|ui| {
+            ui.selectable_value(
+                &mut doc.active_tab,
+                TableTab::Data,
+                format!("{} Data", egui_phosphor::regular::TABLE),
+            );
+            ui.selectable_value(
+                &mut doc.active_tab,
+                TableTab::Query,
+                format!("{} Query result", egui_phosphor::regular::FILE_SQL),
+            );
+            if ui
+                .small_button(egui_phosphor::regular::X)
+                .on_hover_text("Close query result")
+                .clicked()
+            {
+                doc.query_result = None;
+                doc.active_tab = TableTab::Data;
             }
         });
+        ui.separator();
     }
+
+    let (id, tab, is_loading) = (doc.id, doc.active_tab, doc.loading.is_some());
+    // ! Back where the previous session left the table, once fully read
+    let restore_scroll = match (tab, is_loading) {
+        (TableTab::Data, false) => doc.restore_scroll.take(),
+        _ => None,
+    };
+    if let Some(data_tab) = doc.tab_mut(tab) {
+        if restore_scroll.is_some() {
+            data_tab.scroll.restore = restore_scroll;
+        }
+        // ! Sort & filter in the background, superseding the previous run
+        if let Some(view) = render_data_tab(ui, data_tab, is_loading) {
+            if let Some(job_id) = data_tab.view_job.take() {
+                jobs.cancel(job_id);
+            }
+            let (job_id, _) = jobs.start(JobKind::View, id, tab, "Sorting & filtering");
+            data_tab.view_job = Some(job_id);
+            thrd_apply_view(
+                tx.clone(),
+                job_id,
+                data_tab.table.view_source(),
+                data_tab.table.columns.clone(),
+                view,
+            );
+        }
+    }
+
+    picked_sheet
 }
@@ -0,0 +1,75 @@
+use egui::{RichText, ScrollArea};
+
+/// Lists the files of a zip archive that can be opened, one of them is
+/// extracted and opened in its own tab.
+#[derive(Debug, Clone)]
+pub struct ArchivePicker {
+    pub file_path: String,
+    entries: Vec<String>,
+    selected: Option<usize>,
+}
+
+impl ArchivePicker {
+    pub fn new(file_path: String, entries: Vec<String>) -> Self {
+        ArchivePicker {
+            file_path,
+            entries,
+            selected: None,
+        }
+    }
+
+    /// Renders the picker and returns the entry the user chose to open.
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> Option<String> {
+        let mut picked: Option<String> = None;
+        let mut cancelled = false;
+
+        egui::Window::new("Open from archive")
+            .resizable(true)
+            .default_width(400.)
+            .open(open)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(&self.file_path).weak());
+                ui.separator();
+
+                if self.entries.is_empty() {
+                    ui.label("The archive holds no file that can be opened");
+                }
+                ScrollArea::vertical()
+                    .max_height(300.)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for (idx, entry) in self.entries.iter().enumerate() {
+                            let response = ui.selectable_label(
+                                self.selected == Some(idx),
+                                format!("{} {}", egui_phosphor::regular::FILE, entry),
+                            );
+                            if response.clicked() {
+                                self.selected = Some(idx);
+                            }
+                            if response.double_clicked() {
+                                picked = Some(entry.clone());
+                            }
+                        }
+                    });
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let can_open = self.selected.is_some();
+                    if ui
+                        .add_enabled(can_open, egui::Button::new("Open"))
+                        .clicked()
+                    {
+                        picked = self.selected.map(|idx| self.entries[idx].clone());
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+
+        if picked.is_some() || cancelled {
+            *open = false;
+        }
+        picked
+    }
+}
@@ -0,0 +1,195 @@
+use std::{env, fs, path::Path, sync::Arc};
+
+use clap::{Parser, Subcommand};
+use polars::prelude::{DataFrame, IdxSize};
+
+use crate::{
+    enums::{ExportFormat, ParquetCodec, ThemeMode},
+    errors::DavError,
+    models::ImportOptions,
+    read_file::{load_data_from_file, scan_file},
+    write_file::export_dataframe,
+};
+
+/// Opens the viewer, or runs one of the commands without a window.
+#[derive(Debug, Parser)]
+#[command(
+    name = "da_viewer",
+    version,
+    about,
+    args_conflicts_with_subcommands = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Files opened on startup, each in its own tab
+    pub files: Vec<String>,
+    /// Sheet of a workbook, or table of a database, shown first
+    #[arg(long)]
+    pub sheet: Option<String>,
+    /// Delimiter of text files: a single character, or tab, comma,
+    /// semicolon, pipe
+    #[arg(long, value_parser = parse_delimiter)]
+    pub delimiter: Option<u8>,
+    #[arg(long, value_enum)]
+    pub theme: Option<ThemeMode>,
+}
+
+/// Options of the files given on the command line, applied when they open.
+#[derive(Debug, Clone, Default)]
+pub struct LaunchOptions {
+    pub files: Vec<String>,
+    pub sheet: Option<String>,
+    pub delimiter: Option<u8>,
+}
+
+impl Cli {
+    pub fn launch_options(&self) -> LaunchOptions {
+        LaunchOptions {
+            // ! Tabs are keyed by path, a relative path would open twice
+            files: self
+                .files
+                .iter()
+                .map(|file| {
+                    fs::canonicalize(file)
+                        .map(|path| strip_verbatim(&path.to_string_lossy()).replace('\\', "/"))
+                        .unwrap_or_else(|_| file.clone())
+                })
+                .collect(),
+            sheet: self.sheet.clone(),
+            delimiter: self.delimiter,
+        }
+    }
+}
+
+/// `canonicalize` gives `\\?\C:\...` paths on Windows, while the file dialog,
+/// the session and the recent files hold plain `C:\...` ones.
+fn strip_verbatim(path: &str) -> String {
+    match path.strip_prefix(r"\\?\UNC\") {
+        Some(share) => format!(r"\\{}", share),
+        None => path.strip_prefix(r"\\?\").unwrap_or(path).to_string(),
+    }
+}
+
+fn parse_delimiter(value: &str) -> Result<u8, String> {
+    match value {
+        "tab" | "\\t" => Ok(b'\t'),
+        "comma" => Ok(b','),
+        "semicolon" => Ok(b';'),
+        "pipe" => Ok(b'|'),
+        value if value.len() == 1 && value.is_ascii() => Ok(value.as_bytes()[0]),
+        value => Err(format!("`{}` is not a single ASCII character", value)),
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Prints the columns of a file and their types
+    Schema {
+        file: String,
+        /// Sheet of a workbook, or table of a database
+        #[arg(long)]
+        sheet: Option<String>,
+    },
+    /// Prints the first rows of a file as a table
+    Head {
+        file: String,
+        #[arg(short = 'n', long = "rows", default_value = "10")]
+        rows: usize,
+        #[arg(long)]
+        sheet: Option<String>,
+    },
+    /// Converts a file, the output format follows its extension (csv,
+    /// parquet, ndjson, arrow, xlsx)
+    Convert {
+        input: String,
+        output: String,
+        #[arg(long)]
+        sheet: Option<String>,
+    },
+    /// Prints summary statistics of every column
+    Stats {
+        file: String,
+        #[arg(long)]
+        sheet: Option<String>,
+    },
+}
+
+pub fn run(command: Command) -> Result<(), DavError> {
+    match command {
+        Command::Schema { file, sheet } => {
+            // ! Formats with a lazy reader only read their metadata
+            let schema = match scan_file(&file, &ImportOptions::default())? {
+                Some(source) => source.schema()?,
+                None => Arc::new(load(file, sheet)?.schema()),
+            };
+            let width = schema
+                .iter_names()
+                .map(|name| name.len())
+                .max()
+                .unwrap_or(0);
+            for (name, dtype) in schema.iter() {
+                println!("{:width$}  {}", name, dtype, width = width);
+            }
+        }
+        Command::Head { file, rows, sheet } => {
+            // ! Formats with a lazy reader stop after the rows shown
+            let df = match scan_file(&file, &ImportOptions::default())? {
+                Some(source) => source.limit(rows as IdxSize).collect()?,
+                None => load(file, sheet)?.head(Some(rows)),
+            };
+            // ! Polars shortens the printed table unless told otherwise
+            env::set_var("POLARS_FMT_MAX_ROWS", rows.to_string());
+            env::set_var("POLARS_FMT_MAX_COLS", df.width().to_string());
+            println!("{}", df);
+        }
+        Command::Convert {
+            input,
+            output,
+            sheet,
+        } => {
+            let extension = Path::new(&output)
+                .extension()
+                .and_then(|ext| ext.to_str())
+                .unwrap_or_default()
+                .to_lowercase();
+            let format = ExportFormat::from_extension(&extension)
+                .ok_or(DavError::UnsupportedFormat(extension))?;
+            let df = load(input, sheet)?;
+            export_dataframe(&df, &output, format, ParquetCodec::default(), |_| true)?;
+        }
+        Command::Stats { file, sheet } => {
+            let df = load(file, sheet)?;
+            let stats = df.describe(None)?;
+            env::set_var("POLARS_FMT_MAX_ROWS", stats.height().to_string());
+            env::set_var("POLARS_FMT_MAX_COLS", stats.width().to_string());
+            println!("{} rows", df.height());
+            println!("{}", stats);
+        }
+    }
+    Ok(())
+}
+
+/// Reads a file with the same loaders as the window, all at once.
+fn load(file: String, sheet: Option<String>) -> Result<DataFrame, DavError> {
+    let table = load_data_from_file(file, sheet.as_deref(), &ImportOptions::default(), |_, _| {
+        true
+    })?;
+    Ok(table.df)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn verbatim_prefix_is_stripped() {
+        assert_eq!(strip_verbatim(r"\\?\C:\data\a.csv"), r"C:\data\a.csv");
+        assert_eq!(
+            strip_verbatim(r"\\?\UNC\server\share\a.csv"),
+            r"\\server\share\a.csv"
+        );
+        assert_eq!(strip_verbatim("/home/me/a.csv"), "/home/me/a.csv");
+    }
+}
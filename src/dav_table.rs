@@ -1,82 +1,294 @@
-use egui::{RichText, ScrollArea, Ui};
+use egui::{scroll_area, Color32, ComboBox, Id, RichText, ScrollArea, Sense, TextEdit, Ui};
 use egui_extras::{Column, TableBuilder};
 
-use crate::models::TableStruct;
+use crate::{
+    enums::{BoolFilter, TextMatch},
+    models::{ColumnFilter, DataTab, TableScroll, TableStruct, ViewState},
+};
 
-pub fn render_table(ui: &mut Ui, table_struct: &TableStruct) {
+const ROW_HEIGHT: f32 = 30.;
+
+/// Renders a data tab (row count + table) and returns the view to recompute
+/// once the user commits a sort or filter change. Sorting and filtering are
+/// disabled while the rows are still `is_loading`.
+pub fn render_data_tab(ui: &mut Ui, data_tab: &mut DataTab, is_loading: bool) -> Option<ViewState> {
+    let DataTab {
+        table,
+        view,
+        applied_view,
+        selected_row,
+        view_job,
+        scroll,
+    } = data_tab;
+
+    // ! Row count
+    ui.horizontal(|ui| {
+        ui.label(RichText::new(format!("{} of {} rows", table.height(), table.total_rows)).weak());
+        if view_job.is_some() {
+            ui.spinner();
+        }
+    });
+
+    // ! Render table
+    let mut view_changed = false;
+    ui.group(|ui| {
+        view_changed = render_table(ui, table, view, selected_row, scroll, !is_loading);
+    });
+
+    if view_changed && view != applied_view {
+        *applied_view = view.clone();
+        return Some(view.clone());
+    }
+    None
+}
+
+/// Renders the table and returns `true` when the user changed the view (e.g.
+/// sorting) so the caller can recompute it off the UI thread.
+fn render_table(
+    ui: &mut Ui,
+    table_struct: &TableStruct,
+    view: &mut ViewState,
+    selected_row: &mut Option<usize>,
+    scroll: &mut TableScroll,
+    can_change_view: bool,
+) -> bool {
     let font_size = 13.;
+    let mut view_changed = false;
     let max_size_column = table_struct
         .columns
         .iter()
-        .map(|s| s.len())
+        .map(|c| c.name.len())
         .max()
         .unwrap_or(0);
 
-    ScrollArea::new([true; 2])
-        .auto_shrink([false; 2])
-        .show(ui, |ui| {
-            TableBuilder::new(ui)
-                .striped(false)
-                .resizable(true)
-                .columns(
-                    {
-                        if max_size_column == 0 {
-                            Column::auto().resizable(true).clip(true)
+    // ! Only the horizontal axis scrolls here, the table scrolls its own body
+    // ! vertically so the header stays fixed while rows are virtualized
+    let restore = scroll.restore.take();
+    let mut scroll_area = ScrollArea::horizontal().auto_shrink([false; 2]);
+    if let Some([x, _]) = restore {
+        scroll_area = scroll_area.horizontal_scroll_offset(x);
+    }
+    let output = scroll_area.show(ui, |ui| {
+        // ! Id of the scroll area the table puts its body in
+        let body_scroll_id = ui.make_persistent_id(Id::new("scroll_area"));
+
+        let mut builder = TableBuilder::new(ui)
+            .striped(true)
+            .resizable(true)
+            .vscroll(true)
+            .auto_shrink([false; 2]);
+        if let Some([_, y]) = restore {
+            builder = builder.vertical_scroll_offset(y);
+        }
+        builder
+            .columns(
+                {
+                    if max_size_column == 0 {
+                        Column::auto().resizable(true).clip(true)
+                    } else {
+                        Column::initial(max_size_column as f32 * font_size)
+                            .resizable(true)
+                            .clip(true)
+                    }
+                },
+                table_struct.columns.len(),
+            )
+            .header(75., |mut header| {
+                for column in table_struct.columns.iter() {
+                    header.col(|ui| {
+                        ui.set_enabled(can_change_view);
+
+                        let sort_icon = match view.sort_of(&column.name) {
+                            Some(false) => egui_phosphor::regular::SORT_ASCENDING,
+                            Some(true) => egui_phosphor::regular::SORT_DESCENDING,
+                            None => "",
+                        };
+                        let sort_rank = if view.sort.len() > 1 {
+                            view.sort
+                                .iter()
+                                .position(|k| k.column == column.name)
+                                .map(|idx| (idx + 1).to_string())
+                                .unwrap_or_default()
                         } else {
-                            Column::initial(max_size_column as f32 * font_size)
-                                .resizable(true)
-                                .clip(true)
-                        }
-                    },
-                    table_struct.columns.len(),
-                )
-                .header(30., |mut header| {
-                    for column in table_struct.columns.iter() {
-                        header.col(|ui| {
-                            ui.centered_and_justified(|ui| {
-                                ui.label(
-                                    RichText::new({
-                                        if column == "null" || column == "None" || column.is_empty()
+                            String::new()
+                        };
+
+                        let title = ui.vertical_centered(|ui| {
+                            ui.label(
+                                RichText::new(format!(
+                                    "{} {}{}",
+                                    {
+                                        if column.name == "null"
+                                            || column.name == "None"
+                                            || column.name.is_empty()
                                         {
                                             "---"
                                         } else {
-                                            column
+                                            column.name.as_str()
                                         }
-                                    })
-                                    .size(font_size + 7.)
-                                    .strong(),
-                                );
-                            });
+                                    },
+                                    sort_icon,
+                                    sort_rank
+                                ))
+                                .size(font_size + 7.)
+                                .strong(),
+                            );
+                            ui.label(
+                                RichText::new(column.dtype.to_string())
+                                    .size(font_size - 2.)
+                                    .weak(),
+                            );
                         });
-                    }
-                })
-                .body(|mut body| {
-                    body.ui_mut().separator();
-                    for i in 0..table_struct.rows.len() {
-                        body.row(37.5, |mut row| {
-                            for j in 0..table_struct.columns.len() {
-                                row.col(|ui| {
-                                    ui.add_space(7.5);
-
-                                    let text: String = table_struct.rows[i][j]
-                                        .replace('\"', "")
-                                        .trim()
-                                        .to_string();
-
-                                    if text == "null" || text == "None" || text.is_empty() {
-                                        ui.centered_and_justified(|ui| {
-                                            ui.label("---");
-                                        });
-                                    } else {
-                                        ui.horizontal_centered(|ui| {
-                                            ui.label(RichText::new(text).size(font_size));
-                                        });
-                                    }
-                                });
+
+                        // ! Click to sort, shift+click to add a sort key
+                        let response = ui
+                            .interact(
+                                title.response.rect,
+                                ui.id().with(("dav_header", &column.name)),
+                                Sense::click(),
+                            )
+                            .on_hover_text("Click to sort, Shift+Click to add a sort key");
+                        if response.clicked() {
+                            let additive = ui.input(|i| i.modifiers.shift);
+                            view.toggle_sort(&column.name, additive);
+                            view_changed = true;
+                        }
+
+                        // ! Filter
+                        let filter = view
+                            .filters
+                            .entry(column.name.clone())
+                            .or_insert_with(|| ColumnFilter::for_dtype(&column.dtype));
+                        if render_filter(ui, &column.name, filter) {
+                            view_changed = true;
+                        }
+                    });
+                }
+            })
+            .body(|body| {
+                body.rows(ROW_HEIGHT, table_struct.height(), |i, mut row| {
+                    for (j, cell) in table_struct.row_texts(i).into_iter().enumerate() {
+                        row.col(|ui| {
+                            // ! Selected row highlight
+                            if *selected_row == Some(i) {
+                                ui.painter().rect_filled(
+                                    ui.max_rect(),
+                                    0.,
+                                    ui.visuals().selection.bg_fill,
+                                );
+                            }
+
+                            match (table_struct.cell_error(i, j), cell) {
+                                (Some(code), _) => {
+                                    ui.horizontal_centered(|ui| {
+                                        ui.label(
+                                            RichText::new(format!(
+                                                "{} {}",
+                                                egui_phosphor::regular::WARNING,
+                                                code
+                                            ))
+                                            .size(font_size)
+                                            .color(Color32::from_rgb(231, 76, 60)),
+                                        );
+                                    });
+                                }
+                                (None, Some(text)) if !text.trim().is_empty() => {
+                                    ui.horizontal_centered(|ui| {
+                                        ui.label(RichText::new(text.trim()).size(font_size));
+                                    });
+                                }
+                                _ => {
+                                    ui.centered_and_justified(|ui| {
+                                        ui.label("---");
+                                    });
+                                }
+                            }
+
+                            // ! Select row
+                            let response = ui.interact(
+                                ui.max_rect(),
+                                ui.id().with(("dav_cell", i, j)),
+                                Sense::click(),
+                            );
+                            if response.clicked() {
+                                *selected_row = if *selected_row == Some(i) {
+                                    None
+                                } else {
+                                    Some(i)
+                                };
                             }
                         });
-                        body.ui_mut().separator();
                     }
                 });
-        });
+            });
+
+        body_scroll_id
+    });
+
+    let vertical = scroll_area::State::load(ui.ctx(), output.inner).map_or(0., |s| s.offset.y);
+    scroll.offset = [output.state.offset.x, vertical];
+
+    view_changed
+}
+
+/// Predicate editor shown under a column name. Returns `true` once an edit is
+/// committed (text fields on Enter or focus loss) so filtering does not run on
+/// every keystroke.
+fn render_filter(ui: &mut Ui, column: &str, filter: &mut ColumnFilter) -> bool {
+    let mut committed = false;
+
+    ui.horizontal(|ui| match filter {
+        ColumnFilter::Text { mode, value } => {
+            ComboBox::from_id_source(("dav_filter_mode", column))
+                .width(70.)
+                .selected_text(mode.label())
+                .show_ui(ui, |ui| {
+                    for option in [TextMatch::Contains, TextMatch::Regex, TextMatch::Equals] {
+                        if ui.selectable_value(mode, option, option.label()).changed() {
+                            committed = true;
+                        }
+                    }
+                });
+
+            let response = ui.add(
+                TextEdit::singleline(value)
+                    .hint_text(egui_phosphor::regular::FUNNEL)
+                    .desired_width(f32::INFINITY),
+            );
+            committed |= response.lost_focus();
+        }
+        ColumnFilter::Range { min, max } => {
+            let width = (ui.available_width() - ui.spacing().item_spacing.x) / 2.;
+            let response = ui.add(
+                TextEdit::singleline(min)
+                    .hint_text("min")
+                    .desired_width(width),
+            );
+            committed |= response.lost_focus();
+            let response = ui.add(
+                TextEdit::singleline(max)
+                    .hint_text("max")
+                    .desired_width(width),
+            );
+            committed |= response.lost_focus();
+        }
+        ColumnFilter::Bool(value) => {
+            ComboBox::from_id_source(("dav_filter_bool", column))
+                .selected_text(value.label())
+                .show_ui(ui, |ui| {
+                    for option in [
+                        BoolFilter::Any,
+                        BoolFilter::True,
+                        BoolFilter::False,
+                        BoolFilter::Null,
+                    ] {
+                        if ui.selectable_value(value, option, option.label()).changed() {
+                            committed = true;
+                        }
+                    }
+                });
+        }
+    });
+
+    committed
 }
@@ -1,13 +1,53 @@
-use crate::models::TableStruct;
+use polars::prelude::DataFrame;
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+use crate::{
+    errors::DavError,
+    jobs::JobId,
+    models::{LoadProgress, TableStruct},
+};
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
 pub enum ChannelMessage {
     OpenFile(String),
-    ReadFile(TableStruct),
-    ReadFileError(String),
+    /// A file failed before it had a document, e.g. extracting it
+    FileError(String, DavError),
+    SheetNames(JobId, Vec<String>),
+    LoadProgress(JobId, LoadProgress),
+    LoadBatch(JobId, DataFrame),
+    ReadFile(JobId, TableStruct),
+    ReadFileError(JobId, DavError),
+    ViewApplied(JobId, DataFrame),
+    ViewError(JobId, DavError),
+    QueryResult(JobId, TableStruct),
+    QueryError(JobId, DavError),
+    PreviewResult(JobId, DataFrame),
+    PreviewError(JobId, DavError),
+    /// Fraction of the rows written so far
+    ExportProgress(JobId, f32),
+    /// Holds the path written to
+    ExportFinished(JobId, String),
+    ExportError(JobId, DavError),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum JobKind {
+    Load,
+    View,
+    Query,
+    Preview,
+    Export,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TableTab {
+    #[default]
+    Data,
+    Query,
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash, clap::ValueEnum, Serialize, Deserialize)]
 pub enum ThemeMode {
     Light,
     Dark,
@@ -16,3 +56,180 @@ pub enum ThemeMode {
     Macchiato,
     Mocha,
 }
+
+impl ThemeMode {
+    pub fn apply(&self, ctx: &egui::Context) {
+        match self {
+            ThemeMode::Light => ctx.set_visuals(egui::Visuals::light()),
+            ThemeMode::Dark => ctx.set_visuals(egui::Visuals::dark()),
+            ThemeMode::Frappe => catppuccin_egui::set_theme(ctx, catppuccin_egui::FRAPPE),
+            ThemeMode::Latte => catppuccin_egui::set_theme(ctx, catppuccin_egui::LATTE),
+            ThemeMode::Macchiato => catppuccin_egui::set_theme(ctx, catppuccin_egui::MACCHIATO),
+            ThemeMode::Mocha => catppuccin_egui::set_theme(ctx, catppuccin_egui::MOCHA),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TextMatch {
+    #[default]
+    Contains,
+    Regex,
+    Equals,
+}
+
+impl TextMatch {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TextMatch::Contains => "contains",
+            TextMatch::Regex => "regex",
+            TextMatch::Equals => "equals",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum BoolFilter {
+    #[default]
+    Any,
+    True,
+    False,
+    Null,
+}
+
+impl BoolFilter {
+    pub fn label(&self) -> &'static str {
+        match self {
+            BoolFilter::Any => "any",
+            BoolFilter::True => "true",
+            BoolFilter::False => "false",
+            BoolFilter::Null => "null",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum TextEncoding {
+    #[default]
+    Utf8,
+    /// UTF-8, invalid bytes are replaced instead of failing the load
+    Utf8Lossy,
+    /// ISO-8859-1, read as its Windows-1252 superset like browsers do
+    Latin1,
+    Utf16Le,
+    Utf16Be,
+}
+
+impl TextEncoding {
+    pub fn label(&self) -> &'static str {
+        match self {
+            TextEncoding::Utf8 => "UTF-8",
+            TextEncoding::Utf8Lossy => "UTF-8 (lossy)",
+            TextEncoding::Latin1 => "Latin-1 / Windows-1252",
+            TextEncoding::Utf16Le => "UTF-16 LE",
+            TextEncoding::Utf16Be => "UTF-16 BE",
+        }
+    }
+}
+
+/// What a file holds, told from its first bytes or else its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum FileFormat {
+    /// Delimited text
+    Csv,
+    Json,
+    Parquet,
+    /// Arrow IPC file, Feather v2 is the same format
+    Ipc,
+    Avro,
+    Workbook(WorkbookKind),
+    /// Its tables and views are shown like the sheets of a workbook
+    Sqlite,
+    /// Zip archive of other files
+    Archive,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum WorkbookKind {
+    /// Also macro-enabled `.xlsm`
+    Xlsx,
+    Xlsb,
+    Xls,
+    Ods,
+}
+
+/// Single-file compression stream around another format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Compression {
+    Gzip,
+    Zstd,
+    Bzip2,
+}
+
+/// Formats the current view can be exported to.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ExportFormat {
+    #[default]
+    Csv,
+    Parquet,
+    NdJson,
+    Ipc,
+    Xlsx,
+}
+
+impl ExportFormat {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "CSV",
+            ExportFormat::Parquet => "Parquet",
+            ExportFormat::NdJson => "NDJSON",
+            ExportFormat::Ipc => "Arrow IPC",
+            ExportFormat::Xlsx => "Excel (xlsx)",
+        }
+    }
+
+    /// Format written to a file with this extension.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "csv" => Some(ExportFormat::Csv),
+            "parquet" => Some(ExportFormat::Parquet),
+            "ndjson" | "jsonl" => Some(ExportFormat::NdJson),
+            "arrow" | "feather" | "ipc" => Some(ExportFormat::Ipc),
+            "xlsx" => Some(ExportFormat::Xlsx),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ExportFormat::Csv => "csv",
+            ExportFormat::Parquet => "parquet",
+            ExportFormat::NdJson => "ndjson",
+            ExportFormat::Ipc => "arrow",
+            ExportFormat::Xlsx => "xlsx",
+        }
+    }
+}
+
+/// Compression of an exported parquet file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum ParquetCodec {
+    Uncompressed,
+    Snappy,
+    Gzip,
+    Lz4,
+    #[default]
+    Zstd,
+}
+
+impl ParquetCodec {
+    pub fn label(&self) -> &'static str {
+        match self {
+            ParquetCodec::Uncompressed => "None",
+            ParquetCodec::Snappy => "Snappy",
+            ParquetCodec::Gzip => "Gzip",
+            ParquetCodec::Lz4 => "LZ4",
+            ParquetCodec::Zstd => "Zstandard",
+        }
+    }
+}
@@ -0,0 +1,72 @@
+use egui::{CollapsingHeader, Color32, Context, Frame, Label, RichText, Stroke, TopBottomPanel};
+
+use crate::app::AppModel;
+
+/// Lists the errors reported by background jobs, newest last, each one with
+/// its details and a dismiss button. The rest of the window stays usable.
+pub fn render_error_panel(app: &mut AppModel, ctx: &Context) {
+    let error_color = Color32::from_rgb(231, 76, 60);
+    let mut dismissed: Vec<usize> = vec![];
+    let mut dismiss_all = false;
+
+    TopBottomPanel::top("error_panel")
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.add_space(4.);
+            for (idx, report) in app.errors.iter().enumerate() {
+                Frame::group(ui.style())
+                    .stroke(Stroke::new(1., error_color))
+                    .show(ui, |ui| {
+                        ui.horizontal(|ui| {
+                            ui.label(
+                                RichText::new(format!(
+                                    "{} {}",
+                                    egui_phosphor::regular::WARNING,
+                                    report.error.summary()
+                                ))
+                                .color(error_color)
+                                .strong(),
+                            );
+                            ui.label(RichText::new(&report.file_path).weak());
+
+                            ui.with_layout(
+                                egui::Layout::right_to_left(egui::Align::Center),
+                                |ui| {
+                                    if ui
+                                        .small_button(egui_phosphor::regular::X)
+                                        .on_hover_text("Dismiss")
+                                        .clicked()
+                                    {
+                                        dismissed.push(idx);
+                                    }
+                                },
+                            );
+                        });
+
+                        // ! Details
+                        CollapsingHeader::new("Details")
+                            .id_source(("error_details", idx))
+                            .show(ui, |ui| {
+                                let details = report.error.to_string();
+                                ui.add(Label::new(&details).wrap(true));
+                                if ui.small_button("Copy").clicked() {
+                                    ui.output_mut(|o| o.copied_text = details);
+                                }
+                            });
+                    });
+            }
+
+            if app.errors.len() > 1 && ui.button("Dismiss all").clicked() {
+                dismiss_all = true;
+            }
+            ui.add_space(4.);
+        });
+
+    if dismiss_all {
+        app.errors.clear();
+        return;
+    }
+    for idx in dismissed.into_iter().rev() {
+        app.errors.remove(idx);
+    }
+}
@@ -0,0 +1,84 @@
+use std::{fmt, io};
+
+use calamine::Error as CalamineError;
+use polars::prelude::PolarsError;
+use rusqlite::{Error as SqliteError, ErrorCode};
+
+/// Everything that can go wrong while reading a file or computing a view.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DavError {
+    Io(String),
+    Parse(String),
+    /// Holds the file extension
+    UnsupportedFormat(String),
+    SheetMissing(String),
+    Cancelled,
+}
+
+impl DavError {
+    /// One-line headline shown in the error panel, the details come from
+    /// `Display`.
+    pub fn summary(&self) -> &'static str {
+        match self {
+            DavError::Io(_) => "Cannot read file",
+            DavError::Parse(_) => "Cannot parse data",
+            DavError::UnsupportedFormat(_) => "Unsupported format",
+            DavError::SheetMissing(_) => "Sheet not found",
+            DavError::Cancelled => "Cancelled",
+        }
+    }
+}
+
+impl fmt::Display for DavError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            DavError::Io(err) => write!(f, "I/O error: {}", err),
+            DavError::Parse(err) => write!(f, "{}", err),
+            DavError::UnsupportedFormat(ext) if ext.is_empty() => {
+                write!(f, "The format of the file was not recognized")
+            }
+            DavError::UnsupportedFormat(ext) => {
+                write!(f, "`.{}` files are not supported yet", ext)
+            }
+            DavError::SheetMissing(sheet) => write!(f, "The workbook has no sheet `{}`", sheet),
+            DavError::Cancelled => write!(f, "Loading cancelled"),
+        }
+    }
+}
+
+impl std::error::Error for DavError {}
+
+impl From<io::Error> for DavError {
+    fn from(err: io::Error) -> Self {
+        DavError::Io(err.to_string())
+    }
+}
+
+impl From<PolarsError> for DavError {
+    fn from(err: PolarsError) -> Self {
+        match err {
+            PolarsError::Io(err) => DavError::Io(err.to_string()),
+            err => DavError::Parse(err.to_string()),
+        }
+    }
+}
+
+impl From<CalamineError> for DavError {
+    fn from(err: CalamineError) -> Self {
+        match err {
+            CalamineError::Io(err) => DavError::Io(err.to_string()),
+            err => DavError::Parse(err.to_string()),
+        }
+    }
+}
+
+impl From<SqliteError> for DavError {
+    fn from(err: SqliteError) -> Self {
+        match &err {
+            SqliteError::SqliteFailure(code, _) if code.code == ErrorCode::CannotOpen => {
+                DavError::Io(err.to_string())
+            }
+            _ => DavError::Parse(err.to_string()),
+        }
+    }
+}
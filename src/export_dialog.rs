@@ -0,0 +1,135 @@
+use egui::{ComboBox, Grid, RichText, ScrollArea};
+
+use crate::{
+    enums::{ExportFormat, ParquetCodec, TableTab},
+    models::ColumnInfo,
+};
+
+/// Format and columns of an export of the current view. The target file is
+/// picked once the user confirms.
+#[derive(Debug, Clone)]
+pub struct ExportDialog {
+    pub doc_id: usize,
+    pub tab: TableTab,
+    /// Suggested file name, without extension
+    pub file_stem: String,
+    pub format: ExportFormat,
+    pub codec: ParquetCodec,
+    /// Every column of the view, with whether it is exported
+    pub columns: Vec<(String, bool)>,
+}
+
+impl ExportDialog {
+    pub fn new(doc_id: usize, tab: TableTab, file_stem: String, columns: &[ColumnInfo]) -> Self {
+        ExportDialog {
+            doc_id,
+            tab,
+            file_stem,
+            format: ExportFormat::default(),
+            codec: ParquetCodec::default(),
+            columns: columns.iter().map(|c| (c.name.clone(), true)).collect(),
+        }
+    }
+
+    pub fn selected_columns(&self) -> Vec<String> {
+        self.columns
+            .iter()
+            .filter(|(_, selected)| *selected)
+            .map(|(name, _)| name.clone())
+            .collect()
+    }
+
+    /// Renders the dialog and returns `true` once the user asks to export.
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> bool {
+        let mut confirmed = false;
+        let mut cancelled = false;
+
+        egui::Window::new("Export view")
+            .resizable(true)
+            .default_width(360.)
+            .open(open)
+            .show(ctx, |ui| {
+                ui.label(RichText::new("Rows are exported filtered and sorted as shown").weak());
+                ui.separator();
+
+                // ! Format
+                Grid::new("export_options")
+                    .num_columns(2)
+                    .spacing([20., 6.])
+                    .show(ui, |ui| {
+                        ui.label("Format");
+                        ComboBox::from_id_source("export_format")
+                            .selected_text(self.format.label())
+                            .show_ui(ui, |ui| {
+                                for format in [
+                                    ExportFormat::Csv,
+                                    ExportFormat::Parquet,
+                                    ExportFormat::NdJson,
+                                    ExportFormat::Ipc,
+                                    ExportFormat::Xlsx,
+                                ] {
+                                    ui.selectable_value(&mut self.format, format, format.label());
+                                }
+                            });
+                        ui.end_row();
+
+                        if self.format == ExportFormat::Parquet {
+                            ui.label("Compression");
+                            ComboBox::from_id_source("export_codec")
+                                .selected_text(self.codec.label())
+                                .show_ui(ui, |ui| {
+                                    for codec in [
+                                        ParquetCodec::Uncompressed,
+                                        ParquetCodec::Snappy,
+                                        ParquetCodec::Gzip,
+                                        ParquetCodec::Lz4,
+                                        ParquetCodec::Zstd,
+                                    ] {
+                                        ui.selectable_value(&mut self.codec, codec, codec.label());
+                                    }
+                                });
+                            ui.end_row();
+                        }
+                    });
+                ui.separator();
+
+                // ! Columns
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Columns").strong());
+                    if ui.small_button("All").clicked() {
+                        self.columns.iter_mut().for_each(|(_, s)| *s = true);
+                    }
+                    if ui.small_button("None").clicked() {
+                        self.columns.iter_mut().for_each(|(_, s)| *s = false);
+                    }
+                });
+                ScrollArea::vertical()
+                    .max_height(240.)
+                    .auto_shrink([false, true])
+                    .show(ui, |ui| {
+                        for (name, selected) in self.columns.iter_mut() {
+                            ui.checkbox(selected, name.as_str());
+                        }
+                    });
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    let can_export = self.columns.iter().any(|(_, s)| *s);
+                    if ui
+                        .add_enabled(can_export, egui::Button::new("Export..."))
+                        .clicked()
+                    {
+                        confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+
+        if confirmed || cancelled {
+            *open = false;
+        }
+        confirmed
+    }
+}
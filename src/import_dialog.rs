@@ -0,0 +1,263 @@
+use egui::{Color32, ComboBox, DragValue, Grid, RichText, ScrollArea, TextEdit, Ui};
+
+use crate::{
+    enums::TextEncoding,
+    errors::DavError,
+    jobs::JobId,
+    models::{CsvOptions, ImportOptions, TableStruct},
+    read_file::is_text_table,
+};
+
+/// Import options of one document, with a preview of the first rows read
+/// with them. Nothing is reloaded until the user confirms.
+#[derive(Debug, Clone)]
+pub struct ImportDialog {
+    pub doc_id: usize,
+    pub file_path: String,
+    /// Shows the CSV options
+    is_text: bool,
+    pub sheet: Option<String>,
+    pub options: ImportOptions,
+    /// Options the current preview was requested for
+    pub previewed: Option<ImportOptions>,
+    pub preview: Option<TableStruct>,
+    pub error: Option<DavError>,
+    pub preview_job: Option<JobId>,
+}
+
+impl ImportDialog {
+    pub fn new(
+        doc_id: usize,
+        file_path: String,
+        sheet: Option<String>,
+        options: ImportOptions,
+    ) -> Self {
+        ImportDialog {
+            doc_id,
+            is_text: is_text_table(&file_path),
+            file_path,
+            sheet,
+            options,
+            previewed: None,
+            preview: None,
+            error: None,
+            preview_job: None,
+        }
+    }
+
+    /// `true` when the options changed since the last preview request.
+    pub fn needs_preview(&self) -> bool {
+        self.previewed.as_ref() != Some(&self.options)
+    }
+
+    /// Renders the dialog and returns `true` once the user asks to reload the
+    /// file with the options.
+    pub fn show(&mut self, ctx: &egui::Context, open: &mut bool) -> bool {
+        let mut confirmed = false;
+        let mut cancelled = false;
+
+        egui::Window::new("Import options")
+            .resizable(true)
+            .default_width(600.)
+            .open(open)
+            .show(ctx, |ui| {
+                ui.label(RichText::new(&self.file_path).weak());
+                if let Some(sheet) = &self.sheet {
+                    ui.label(format!("Sheet: {}", sheet));
+                }
+                ui.separator();
+
+                // ! Options
+                Grid::new("import_options")
+                    .num_columns(2)
+                    .spacing([20., 6.])
+                    .show(ui, |ui| {
+                        ui.label("Header");
+                        ui.checkbox(&mut self.options.has_header, "First row is a header");
+                        ui.end_row();
+
+                        ui.label(if self.options.has_header {
+                            "Header row"
+                        } else {
+                            "First row"
+                        });
+                        ui.horizontal(|ui| {
+                            let mut auto = self.options.header_row.is_none();
+                            if ui.checkbox(&mut auto, "Detect").changed() {
+                                self.options.header_row = if auto { None } else { Some(0) };
+                            }
+                            if let Some(header_row) = &mut self.options.header_row {
+                                // ! Shown 1-based like in a spreadsheet
+                                let mut row = *header_row + 1;
+                                ui.add(DragValue::new(&mut row).clamp_range(1..=usize::MAX));
+                                *header_row = row - 1;
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Skip rows");
+                        ui.horizontal(|ui| {
+                            ui.add(DragValue::new(&mut self.options.skip_rows));
+                            ui.label(RichText::new("after the header").weak());
+                        });
+                        ui.end_row();
+
+                        if self.is_text {
+                            render_csv_options(ui, &mut self.options.csv);
+                        }
+                    });
+                ui.separator();
+
+                // ! Preview
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("Preview").strong());
+                    if self.preview_job.is_some() {
+                        ui.spinner();
+                    }
+                });
+                if let Some(err) = &self.error {
+                    ui.label(
+                        RichText::new(format!("{} {}", egui_phosphor::regular::WARNING, err))
+                            .color(Color32::from_rgb(231, 76, 60)),
+                    );
+                } else if let Some(preview) = &self.preview {
+                    render_preview(ui, preview);
+                }
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    if ui.button("Load").clicked() {
+                        confirmed = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancelled = true;
+                    }
+                });
+            });
+
+        if confirmed || cancelled {
+            *open = false;
+        }
+        confirmed
+    }
+}
+
+/// Rows of the options grid that only apply to delimited text.
+fn render_csv_options(ui: &mut Ui, csv: &mut CsvOptions) {
+    ui.label("Delimiter");
+    ui.horizontal(|ui| {
+        let presets = [
+            (None, "Detect"),
+            (Some(b','), "Comma"),
+            (Some(b';'), "Semicolon"),
+            (Some(b'\t'), "Tab"),
+            (Some(b'|'), "Pipe"),
+        ];
+        let selected = presets
+            .iter()
+            .find(|(d, _)| *d == csv.delimiter)
+            .map_or("Other", |(_, label)| *label);
+        ComboBox::from_id_source("import_delimiter")
+            .selected_text(selected)
+            .show_ui(ui, |ui| {
+                for (delimiter, label) in presets {
+                    ui.selectable_value(&mut csv.delimiter, delimiter, label);
+                }
+            });
+        char_field(ui, &mut csv.delimiter, "other");
+    });
+    ui.end_row();
+
+    ui.label("Quote");
+    ui.horizontal(|ui| {
+        char_field(ui, &mut csv.quote, "none");
+        ui.label("Escape");
+        char_field(ui, &mut csv.escape, "\"\"");
+    });
+    ui.end_row();
+
+    ui.label("Encoding");
+    ComboBox::from_id_source("import_encoding")
+        .selected_text(csv.encoding.label())
+        .show_ui(ui, |ui| {
+            for encoding in [
+                TextEncoding::Utf8,
+                TextEncoding::Utf8Lossy,
+                TextEncoding::Latin1,
+                TextEncoding::Utf16Le,
+                TextEncoding::Utf16Be,
+            ] {
+                ui.selectable_value(&mut csv.encoding, encoding, encoding.label());
+            }
+        });
+    ui.end_row();
+
+    ui.label("Comment");
+    ui.horizontal(|ui| {
+        char_field(ui, &mut csv.comment, "none");
+        ui.label(RichText::new("lines starting with it are skipped").weak());
+    });
+    ui.end_row();
+
+    ui.label("Infer types from");
+    ui.horizontal(|ui| {
+        let mut all_rows = csv.infer_schema_length.is_none();
+        if ui.checkbox(&mut all_rows, "All rows").changed() {
+            csv.infer_schema_length = if all_rows { None } else { Some(100) };
+        }
+        if let Some(length) = &mut csv.infer_schema_length {
+            ui.add(
+                DragValue::new(length)
+                    .clamp_range(1..=usize::MAX)
+                    .suffix(" rows"),
+            );
+        }
+    });
+    ui.end_row();
+}
+
+/// Single ASCII character field, empty means `None`.
+fn char_field(ui: &mut Ui, value: &mut Option<u8>, hint: &str) {
+    let mut text = value.map(|b| (b as char).to_string()).unwrap_or_default();
+    let response = ui.add(
+        TextEdit::singleline(&mut text)
+            .hint_text(hint)
+            .desired_width(24.),
+    );
+    if response.changed() {
+        *value = text
+            .chars()
+            .last()
+            .filter(|c| c.is_ascii())
+            .map(|c| c as u8);
+    }
+}
+
+fn render_preview(ui: &mut Ui, preview: &TableStruct) {
+    ScrollArea::both()
+        .max_height(300.)
+        .auto_shrink([false, true])
+        .show(ui, |ui| {
+            Grid::new("import_preview")
+                .striped(true)
+                .min_col_width(60.)
+                .show(ui, |ui| {
+                    for column in preview.columns.iter() {
+                        ui.label(RichText::new(&column.name).strong());
+                    }
+                    ui.end_row();
+
+                    for column in preview.columns.iter() {
+                        ui.label(RichText::new(column.dtype.to_string()).weak());
+                    }
+                    ui.end_row();
+
+                    for i in 0..preview.height() {
+                        for cell in preview.row_texts(i) {
+                            ui.label(cell.unwrap_or_else(|| "---".to_string()));
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+}
@@ -0,0 +1,103 @@
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    time::Instant,
+};
+
+use crate::enums::{JobKind, TableTab};
+
+pub type JobId = usize;
+
+/// A background task (load, query, ...) working for one document.
+#[derive(Debug, Clone)]
+pub struct Job {
+    pub id: JobId,
+    pub kind: JobKind,
+    pub doc_id: usize,
+    pub tab: TableTab,
+    pub label: String,
+    pub started_at: Instant,
+    /// Fraction done, for the jobs that can tell
+    pub progress: Option<f32>,
+    pub cancel: Arc<AtomicBool>,
+}
+
+/// Keeps track of the running jobs. Results whose job is no longer tracked
+/// (cancelled, or replaced by a newer job) are stale and must be dropped.
+#[derive(Debug, Default)]
+pub struct JobManager {
+    next_id: JobId,
+    jobs: Vec<Job>,
+}
+
+impl JobManager {
+    pub fn start(
+        &mut self,
+        kind: JobKind,
+        doc_id: usize,
+        tab: TableTab,
+        label: impl Into<String>,
+    ) -> (JobId, Arc<AtomicBool>) {
+        let id = self.next_id;
+        self.next_id += 1;
+
+        let cancel = Arc::new(AtomicBool::new(false));
+        self.jobs.push(Job {
+            id,
+            kind,
+            doc_id,
+            tab,
+            label: label.into(),
+            started_at: Instant::now(),
+            progress: None,
+            cancel: cancel.clone(),
+        });
+
+        (id, cancel)
+    }
+
+    pub fn get(&self, id: JobId) -> Option<&Job> {
+        self.jobs.iter().find(|j| j.id == id)
+    }
+
+    pub fn is_running(&self, id: JobId) -> bool {
+        self.get(id).is_some()
+    }
+
+    pub fn set_progress(&mut self, id: JobId, fraction: f32) {
+        if let Some(job) = self.jobs.iter_mut().find(|j| j.id == id) {
+            job.progress = Some(fraction);
+        }
+    }
+
+    /// Stops tracking a job that delivered its result. `None` means the
+    /// result is stale.
+    pub fn finish(&mut self, id: JobId) -> Option<Job> {
+        let idx = self.jobs.iter().position(|j| j.id == id)?;
+        Some(self.jobs.remove(idx))
+    }
+
+    pub fn cancel(&mut self, id: JobId) {
+        if let Some(job) = self.finish(id) {
+            job.cancel.store(true, Ordering::Relaxed);
+        }
+    }
+
+    pub fn cancel_document(&mut self, doc_id: usize) {
+        let ids = self
+            .jobs
+            .iter()
+            .filter(|j| j.doc_id == doc_id)
+            .map(|j| j.id)
+            .collect::<Vec<JobId>>();
+        for id in ids {
+            self.cancel(id);
+        }
+    }
+
+    pub fn jobs(&self) -> &[Job] {
+        &self.jobs
+    }
+}
@@ -2,15 +2,30 @@
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
 use app::AppModel;
+use clap::Parser;
+use cli::Cli;
+use settings::Settings;
 
 mod about_window;
 mod app;
+mod archive_picker;
+mod cli;
 mod dav_table;
 mod enums;
+mod error_panel;
+mod errors;
+mod export_dialog;
+mod import_dialog;
+mod jobs;
 mod models;
 mod read_file;
+mod recent_files;
+mod settings;
+mod sql_console;
+mod status_bar;
 mod top_bar;
 mod utils;
+mod write_file;
 
 #[cfg(not(target_arch = "wasm32"))]
 fn main() -> eframe::Result<()> {
@@ -18,11 +33,28 @@ fn main() -> eframe::Result<()> {
     use egui::vec2;
     use tokio::runtime::Runtime;
 
+    #[cfg(windows)]
+    attach_parent_console();
     env_logger::init();
 
+    // ! Subcommands run headless and exit
+    let mut cli = Cli::parse();
+    if let Some(command) = cli.command.take() {
+        if let Err(err) = cli::run(command) {
+            eprintln!("[!] {}", err);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
     let rt = Runtime::new().expect("Unable to create Runtime");
     let _enter = rt.enter();
 
+    let settings = Settings::load();
+    let window_size = settings
+        .window_size
+        .map_or(vec2(850., 590.), |[width, height]| vec2(width, height));
+
     eframe::run_native(
         "DA Viewer",
         NativeOptions {
@@ -35,17 +67,35 @@ fn main() -> eframe::Result<()> {
             follow_system_theme: false,
             drag_and_drop_support: true,
             min_window_size: Some(vec2(222., 123.)),
-            initial_window_size: Some(vec2(850., 590.)),
+            initial_window_size: Some(window_size),
             hardware_acceleration: HardwareAcceleration::Preferred,
             icon_data: Some(
                 IconData::try_from_png_bytes(&include_bytes!("../assets/dav.png")[..]).unwrap(),
             ),
             ..Default::default()
         },
-        Box::new(|cc| Box::new(AppModel::new(cc))),
+        Box::new(move |cc| Box::new(AppModel::new(cc, settings, cli.launch_options(), cli.theme))),
     )
 }
 
+/// Release builds are GUI apps on Windows and start without a console, so the
+/// output of subcommands, `--help` included, is sent to the terminal the
+/// viewer was started from.
+#[cfg(windows)]
+fn attach_parent_console() {
+    const ATTACH_PARENT_PROCESS: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    extern "system" {
+        fn AttachConsole(process_id: u32) -> i32;
+    }
+
+    // ! Fails when started from Explorer, there is no console to attach to
+    unsafe {
+        AttachConsole(ATTACH_PARENT_PROCESS);
+    }
+}
+
 // #[cfg(target_arch = "wasm32")]
 // fn main() {
 //     eframe::WebLogger::init(log::LevelFilter::Debug).ok();
@@ -1,5 +1,522 @@
+use std::{collections::BTreeMap, fmt};
+
+use chrono::{NaiveDate, NaiveDateTime};
+use polars::prelude::{
+    col, lit, AnyValue, DataFrame, DataType, Expr, IntoLazy, LazyFrame, PolarsResult, TimeUnit,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    enums::{BoolFilter, TableTab, TextEncoding, TextMatch},
+    errors::DavError,
+    jobs::JobId,
+};
+
 #[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ColumnInfo {
+    pub name: String,
+    pub dtype: DataType,
+}
+
+#[derive(Clone)]
 pub struct TableStruct {
-    pub columns: Vec<String>,
-    pub rows: Vec<Vec<String>>,
+    pub columns: Vec<ColumnInfo>,
+    /// Rows currently shown, i.e. `source` with the view applied
+    pub df: DataFrame,
+    /// Untouched data as loaded, every view is recomputed from it
+    pub source: LazyFrame,
+    /// Row count of `source`, before any filter
+    pub total_rows: usize,
+    /// Formula errors (`#DIV/0!`, `#N/A`, ...) by column and `source` row.
+    /// The cells themselves are null so the column keeps its type.
+    pub errors: CellErrors,
+    /// `source` row of each shown row, `None` while they are the same
+    pub rows: Option<Vec<usize>>,
+}
+
+/// Error codes of a sheet, keyed by column name then row.
+pub type CellErrors = BTreeMap<String, BTreeMap<usize, String>>;
+
+/// Column holding the `source` row while a view is applied to a table with
+/// error cells, stripped again once the view is back.
+const ROW_INDEX: &str = "__dav_row";
+
+impl TableStruct {
+    pub fn new(df: DataFrame) -> Self {
+        let columns = df
+            .get_columns()
+            .iter()
+            .map(|s| ColumnInfo {
+                name: s.name().to_string(),
+                dtype: s.dtype().clone(),
+            })
+            .collect::<Vec<ColumnInfo>>();
+
+        TableStruct {
+            columns,
+            source: df.clone().lazy(),
+            total_rows: df.height(),
+            df,
+            errors: CellErrors::new(),
+            rows: None,
+        }
+    }
+
+    pub fn with_errors(self, errors: CellErrors) -> Self {
+        TableStruct { errors, ..self }
+    }
+
+    /// Keeps `source` as the given (possibly scanning) plan so later views are
+    /// pushed down to the file instead of filtering everything in memory.
+    pub fn from_lazy(source: LazyFrame) -> PolarsResult<Self> {
+        let df = source.clone().collect()?;
+        Ok(TableStruct::with_source(df, source))
+    }
+
+    /// Same as [`TableStruct::from_lazy`] for data that was already read.
+    pub fn with_source(df: DataFrame, source: LazyFrame) -> Self {
+        TableStruct {
+            source,
+            ..TableStruct::new(df)
+        }
+    }
+
+    pub fn height(&self) -> usize {
+        self.df.height()
+    }
+
+    /// Plan to apply views on, tagging rows with their `source` index when
+    /// error cells have to follow them through sorts and filters.
+    pub fn view_source(&self) -> LazyFrame {
+        if self.errors.is_empty() {
+            self.source.clone()
+        } else {
+            self.source.clone().with_row_count(ROW_INDEX, None)
+        }
+    }
+
+    /// Shows the rows of a view computed on [`TableStruct::view_source`].
+    pub fn set_view(&mut self, mut df: DataFrame) {
+        self.rows = df
+            .drop_in_place(ROW_INDEX)
+            .ok()
+            .and_then(|s| s.idx().ok().cloned())
+            .map(|idx| {
+                idx.into_iter()
+                    .map(|row| row.unwrap_or_default() as usize)
+                    .collect::<Vec<usize>>()
+            });
+        self.df = df;
+    }
+
+    /// Error code of a shown cell, if the sheet had one there.
+    pub fn cell_error(&self, row: usize, col: usize) -> Option<&str> {
+        let errors = self.errors.get(&self.columns.get(col)?.name)?;
+        let row = match &self.rows {
+            Some(rows) => *rows.get(row)?,
+            None => row,
+        };
+        errors.get(&row).map(|code| code.as_str())
+    }
+
+    /// Formats a single cell for display, `None` when the value is null.
+    pub fn cell_text(&self, row: usize, col: usize) -> Option<String> {
+        let value = self.df.get_columns()[col].get(row).ok()?;
+        match value {
+            AnyValue::Null => None,
+            AnyValue::Utf8(s) => Some(s.to_string()),
+            AnyValue::Utf8Owned(s) => Some(s.to_string()),
+            AnyValue::Duration(value, unit) => Some(format_duration(match unit {
+                TimeUnit::Nanoseconds => value / 1_000_000,
+                TimeUnit::Microseconds => value / 1_000,
+                TimeUnit::Milliseconds => value,
+            })),
+            other => Some(other.to_string()),
+        }
+    }
+
+    /// Materializes one row, walking every column at the same row index so the
+    /// cells line up with `columns`.
+    pub fn row_texts(&self, row: usize) -> Vec<Option<String>> {
+        (0..self.columns.len())
+            .map(|col| self.cell_text(row, col))
+            .collect::<Vec<Option<String>>>()
+    }
+}
+
+/// Formats milliseconds as `h:mm:ss`, hours are not wrapped into days.
+pub fn format_duration(millis: i64) -> String {
+    let sign = if millis < 0 { "-" } else { "" };
+    let seconds = (millis.abs() + 500) / 1_000;
+    format!(
+        "{}{}:{:02}:{:02}",
+        sign,
+        seconds / 3_600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+// `LazyFrame` has no `Debug`, so the plan is left out
+impl fmt::Debug for TableStruct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("TableStruct")
+            .field("columns", &self.columns)
+            .field("df", &self.df)
+            .field("total_rows", &self.total_rows)
+            .field("errors", &self.errors)
+            .finish_non_exhaustive()
+    }
+}
+
+/// An opened file, shown as one tab of the window.
+#[derive(Debug, Clone)]
+pub struct Document {
+    pub id: usize,
+    pub file_path: String,
+    pub data: Option<DataTab>,
+    pub query_result: Option<DataTab>,
+    pub active_tab: TableTab,
+    /// SQLite database, `sheets` then holds its tables and views and the SQL
+    /// console queries the database
+    pub is_database: bool,
+    /// Sheets of a workbook, empty for other formats
+    pub sheets: Vec<String>,
+    /// Sheet shown in `data`
+    pub active_sheet: Option<String>,
+    /// Sheets read before and put aside while another sheet is shown
+    pub sheet_cache: BTreeMap<String, DataTab>,
+    pub import_options: ImportOptions,
+    /// Set while the file is still being read, `data` then holds the rows
+    /// received so far
+    pub loading: Option<LoadState>,
+    /// Scroll offset of the previous session, applied once the file is read
+    pub restore_scroll: Option<[f32; 2]>,
+}
+
+impl Document {
+    pub fn new(id: usize, file_path: String) -> Self {
+        Document {
+            id,
+            file_path,
+            data: None,
+            query_result: None,
+            active_tab: TableTab::Data,
+            is_database: false,
+            sheets: vec![],
+            active_sheet: None,
+            sheet_cache: BTreeMap::new(),
+            import_options: ImportOptions::default(),
+            loading: None,
+            restore_scroll: None,
+        }
+    }
+
+    pub fn title(&self) -> &str {
+        self.file_path
+            .rsplit('/')
+            .next()
+            .unwrap_or(self.file_path.as_str())
+    }
+
+    /// Title of its tab, naming the table too since a database can be open
+    /// in several tabs.
+    pub fn tab_label(&self) -> String {
+        match &self.active_sheet {
+            Some(table) if self.is_database => format!("{} · {}", self.title(), table),
+            _ => self.title().to_string(),
+        }
+    }
+
+    pub fn tab(&self, tab: TableTab) -> Option<&DataTab> {
+        match tab {
+            TableTab::Data => self.data.as_ref(),
+            TableTab::Query => self.query_result.as_ref(),
+        }
+    }
+
+    pub fn tab_mut(&mut self, tab: TableTab) -> Option<&mut DataTab> {
+        match tab {
+            TableTab::Data => self.data.as_mut(),
+            TableTab::Query => self.query_result.as_mut(),
+        }
+    }
+}
+
+/// How the rows of a text table or a sheet are turned into a header and data.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct ImportOptions {
+    /// Index of the header row, the rows above it are skipped. `None` detects
+    /// it in workbooks and picks the first row of text files
+    pub header_row: Option<usize>,
+    /// Without a header, columns are named `column_1..n` and `header_row` is
+    /// the first data row
+    pub has_header: bool,
+    /// Rows skipped between the header and the data
+    pub skip_rows: usize,
+    pub csv: CsvOptions,
+}
+
+impl Default for ImportOptions {
+    fn default() -> Self {
+        ImportOptions {
+            header_row: None,
+            has_header: true,
+            skip_rows: 0,
+            csv: CsvOptions::default(),
+        }
+    }
+}
+
+/// Parsing options of delimited text files.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct CsvOptions {
+    /// `None` sniffs it from the first lines
+    pub delimiter: Option<u8>,
+    /// `None` disables quoting
+    pub quote: Option<u8>,
+    /// `None` when quotes are escaped by doubling them
+    pub escape: Option<u8>,
+    pub encoding: TextEncoding,
+    /// Lines starting with it are skipped
+    pub comment: Option<u8>,
+    /// Rows read to infer the column types, `None` reads the whole file
+    pub infer_schema_length: Option<usize>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        CsvOptions {
+            delimiter: None,
+            quote: Some(b'"'),
+            escape: None,
+            encoding: TextEncoding::default(),
+            comment: None,
+            infer_schema_length: Some(100),
+        }
+    }
+}
+
+/// An error shown in the error panel until the user dismisses it.
+#[derive(Debug, Clone)]
+pub struct ErrorReport {
+    pub file_path: String,
+    pub error: DavError,
+}
+
+#[derive(Debug, Clone)]
+pub struct LoadState {
+    pub job_id: JobId,
+    pub progress: LoadProgress,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LoadProgress {
+    pub rows: usize,
+    /// Bytes read so far, estimated for text formats
+    pub bytes: u64,
+    pub total_bytes: u64,
+    /// `None` when the total is unknown
+    pub fraction: Option<f32>,
+}
+
+/// A table on screen together with the sort/filter/selection state picked
+/// for it.
+#[derive(Debug, Clone)]
+pub struct DataTab {
+    pub table: TableStruct,
+    pub view: ViewState,
+    /// Last view sent to the background thread, to skip redundant recomputes
+    pub applied_view: ViewState,
+    /// Row index into `table.df`
+    pub selected_row: Option<usize>,
+    /// Job recomputing `applied_view`, results of older jobs are dropped
+    pub view_job: Option<JobId>,
+    pub scroll: TableScroll,
+}
+
+/// Scroll position of a table, kept to restore it in the next session.
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct TableScroll {
+    /// Horizontal and vertical offset, in points
+    pub offset: [f32; 2],
+    /// Offset to jump to on the next frame
+    pub restore: Option<[f32; 2]>,
+}
+
+impl DataTab {
+    pub fn new(table: TableStruct) -> Self {
+        DataTab {
+            table,
+            view: ViewState::default(),
+            applied_view: ViewState::default(),
+            selected_row: None,
+            view_job: None,
+            scroll: TableScroll::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct SortKey {
+    pub column: String,
+    pub descending: bool,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum ColumnFilter {
+    Text { mode: TextMatch, value: String },
+    Range { min: String, max: String },
+    Bool(BoolFilter),
+}
+
+impl ColumnFilter {
+    pub fn for_dtype(dtype: &DataType) -> Self {
+        match dtype {
+            DataType::Boolean => ColumnFilter::Bool(BoolFilter::Any),
+            DataType::Date | DataType::Datetime(_, _) => ColumnFilter::Range {
+                min: String::new(),
+                max: String::new(),
+            },
+            dtype if dtype.is_numeric() => ColumnFilter::Range {
+                min: String::new(),
+                max: String::new(),
+            },
+            _ => ColumnFilter::Text {
+                mode: TextMatch::default(),
+                value: String::new(),
+            },
+        }
+    }
+
+    /// Turns the predicate into a Polars expression, `None` when it does not
+    /// filter anything (or a bound cannot be parsed for the column dtype).
+    pub fn to_expr(&self, column: &str, dtype: &DataType) -> Option<Expr> {
+        match self {
+            ColumnFilter::Text { mode, value } => {
+                if value.is_empty() {
+                    return None;
+                }
+                let text = col(column).cast(DataType::Utf8);
+                Some(match mode {
+                    TextMatch::Contains => text.str().contains_literal(lit(value.as_str())),
+                    TextMatch::Regex => text.str().contains(lit(value.as_str()), true),
+                    TextMatch::Equals => text.eq(lit(value.as_str())),
+                })
+            }
+            ColumnFilter::Range { min, max } => {
+                let lower = range_bound(min, dtype).map(|bound| col(column).gt_eq(bound));
+                let upper = range_bound(max, dtype).map(|bound| col(column).lt_eq(bound));
+                match (lower, upper) {
+                    (Some(lower), Some(upper)) => Some(lower.and(upper)),
+                    (lower, upper) => lower.or(upper),
+                }
+            }
+            ColumnFilter::Bool(filter) => match filter {
+                BoolFilter::Any => None,
+                BoolFilter::True => Some(col(column).eq(lit(true))),
+                BoolFilter::False => Some(col(column).eq(lit(false))),
+                BoolFilter::Null => Some(col(column).is_null()),
+            },
+        }
+    }
+}
+
+/// Parses a range bound typed by the user into a literal matching the column.
+fn range_bound(text: &str, dtype: &DataType) -> Option<Expr> {
+    let text = text.trim();
+    if text.is_empty() {
+        return None;
+    }
+
+    match dtype {
+        DataType::Date => parse_datetime(text).map(|dt| lit(dt.date())),
+        DataType::Datetime(_, _) => parse_datetime(text).map(lit),
+        _ => text.parse::<f64>().ok().map(lit),
+    }
+}
+
+fn parse_datetime(text: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S")
+        .or_else(|_| NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S"))
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Hash)]
+pub struct ViewState {
+    pub sort: Vec<SortKey>,
+    pub filters: BTreeMap<String, ColumnFilter>,
+}
+
+impl ViewState {
+    /// Cycles a column through ascending, descending and unsorted. Without
+    /// `additive` the column becomes the only sort key.
+    pub fn toggle_sort(&mut self, column: &str, additive: bool) {
+        let next = match self.sort_of(column) {
+            None => Some(false),
+            Some(false) => Some(true),
+            Some(true) => None,
+        };
+
+        if !additive {
+            self.sort.retain(|k| k.column == column);
+        }
+
+        match (self.sort.iter().position(|k| k.column == column), next) {
+            (Some(idx), Some(descending)) => self.sort[idx].descending = descending,
+            (Some(idx), None) => {
+                self.sort.remove(idx);
+            }
+            (None, Some(descending)) => self.sort.push(SortKey {
+                column: column.to_string(),
+                descending,
+            }),
+            (None, None) => {}
+        }
+    }
+
+    /// `Some(descending)` when the column is one of the sort keys.
+    pub fn sort_of(&self, column: &str) -> Option<bool> {
+        self.sort
+            .iter()
+            .find(|k| k.column == column)
+            .map(|k| k.descending)
+    }
+
+    /// Builds the query for this view on top of the source data. Filters are
+    /// combined into a single predicate so Polars can push it down to the scan.
+    /// Polars sorts by the column dtype so numbers, text and dates each order
+    /// naturally.
+    pub fn apply(&self, source: LazyFrame, columns: &[ColumnInfo]) -> LazyFrame {
+        let predicate = columns
+            .iter()
+            .filter_map(|c| {
+                self.filters
+                    .get(&c.name)
+                    .and_then(|filter| filter.to_expr(&c.name, &c.dtype))
+            })
+            .reduce(|acc, expr| acc.and(expr));
+
+        let lf = match predicate {
+            Some(predicate) => source.filter(predicate),
+            None => source,
+        };
+
+        if self.sort.is_empty() {
+            return lf;
+        }
+
+        let by_exprs = self.sort.iter().map(|k| col(&k.column)).collect::<Vec<_>>();
+        let descending = self
+            .sort
+            .iter()
+            .map(|k| k.descending)
+            .collect::<Vec<bool>>();
+        lf.sort_by_exprs(by_exprs, descending, true, true)
+    }
 }
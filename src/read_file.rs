@@ -1,151 +1,1588 @@
-use std::{fs, io::Cursor, path::Path};
+use std::{
+    cell::Cell,
+    cmp::Reverse,
+    collections::{hash_map::DefaultHasher, BTreeMap},
+    env,
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{self, BufRead, BufReader, Cursor, Read, Write},
+    path::{Path, PathBuf},
+    rc::Rc,
+};
 
-use calamine::{open_workbook, DataType, Reader, Xlsx};
-use polars::prelude::{JsonReader, LazyCsvReader, LazyFileListReader, LazyFrame, SerReader};
+use bzip2::read::BzDecoder;
 
-use crate::models::TableStruct;
+use calamine::{
+    open_workbook, DataType, Error as CalamineError, Ods, Range, Reader, Sheets, Xls, Xlsb, Xlsx,
+};
+use chrono::{NaiveDate, NaiveDateTime, Timelike};
+use flate2::read::MultiGzDecoder;
+use polars::{
+    io::{avro::AvroReader, mmap::MmapBytesReader},
+    prelude::{
+        count, CsvEncoding, CsvReader, DataFrame, DataType as DType, IdxSize, JsonFormat,
+        JsonReader, LazyCsvReader, LazyFileListReader, LazyFrame, NamedFrom, ParquetReader,
+        PolarsResult, SerReader, Series, TimeUnit,
+    },
+};
+use rusqlite::{types::Value as SqlValue, Connection, OpenFlags};
 
-pub fn load_data_from_file(file_path: String) -> Result<TableStruct, String> {
-    let path = Path::new(&file_path);
-    match path.extension().unwrap().to_str().unwrap() {
-        "csv" => read_csv(file_path),
-        "parquet" => read_parquet(file_path),
-        "json" => read_json(file_path),
-        "xlsx" => read_excel(file_path),
-        _ => Err("File extension not supported yet".to_string()),
+use crate::{
+    enums::{Compression, FileFormat, TextEncoding, WorkbookKind},
+    errors::DavError,
+    models::{format_duration, CellErrors, CsvOptions, ImportOptions, LoadProgress, TableStruct},
+};
+use zip::ZipArchive;
+
+/// Rows per batch handed to the UI while a file is streamed in.
+const BATCH_ROWS: usize = 50_000;
+
+/// Spreadsheet formats calamine reads: Excel (current, macro-enabled, legacy
+/// and binary) and OpenDocument.
+pub const WORKBOOK_EXTENSIONS: [&str; 5] = ["xlsx", "xlsm", "xls", "xlsb", "ods"];
+
+/// Delimited text formats, all read as CSV.
+pub const TEXT_EXTENSIONS: [&str; 4] = ["csv", "tsv", "txt", "psv"];
+
+/// JSON documents and JSON Lines.
+pub const JSON_EXTENSIONS: [&str; 3] = ["json", "jsonl", "ndjson"];
+
+/// Single-file compression streams, decompressed before reading.
+pub const COMPRESSED_EXTENSIONS: [&str; 3] = ["gz", "zst", "bz2"];
+
+/// SQLite databases.
+pub const SQLITE_EXTENSIONS: [&str; 3] = ["sqlite", "sqlite3", "db"];
+
+/// Arrow IPC files, Feather v2 included.
+pub const IPC_EXTENSIONS: [&str; 3] = ["arrow", "feather", "ipc"];
+
+/// Rows scanned when looking for the header row of a sheet.
+const HEADER_SCAN_ROWS: usize = 20;
+
+/// Delimiters tried when sniffing a text file, in order of preference.
+const DELIMITERS: [u8; 4] = [b',', b';', b'\t', b'|'];
+/// Lines compared when sniffing the delimiter
+const SNIFF_LINES: usize = 20;
+/// Compressed bytes decompressed between two progress reports
+const PROGRESS_BYTES: u64 = 1024 * 1024;
+/// Bytes read at the start of a text file to sniff it
+const SAMPLE_BYTES: usize = 64 * 1024;
+/// Bytes read at the start of any file to tell its format
+const DETECT_BYTES: usize = 4 * 1024;
+
+const PARQUET_MAGIC: &[u8] = b"PAR1";
+const ARROW_MAGIC: &[u8] = b"ARROW1";
+/// Avro object container file
+const AVRO_MAGIC: &[u8] = b"Obj\x01";
+const ZIP_MAGIC: &[u8] = b"PK\x03\x04";
+const SQLITE_MAGIC: &[u8] = b"SQLite format 3\0";
+const GZIP_MAGIC: &[u8] = &[0x1F, 0x8B];
+const ZSTD_MAGIC: &[u8] = &[0x28, 0xB5, 0x2F, 0xFD];
+const BZIP2_MAGIC: &[u8] = b"BZh";
+/// OLE2 compound file, the container of legacy `.xls` workbooks
+const OLE_MAGIC: &[u8] = &[0xD0, 0xCF, 0x11, 0xE0, 0xA1, 0xB1, 0x1A, 0xE1];
+
+/// Reads a file, calling `on_batch` with all the rows read so far after each
+/// batch for the formats that can be streamed. Returning `false` from
+/// `on_batch` cancels the load. Workbooks read `sheet`, or their first sheet.
+pub fn load_data_from_file(
+    file_path: String,
+    sheet: Option<&str>,
+    options: &ImportOptions,
+    on_batch: impl FnMut(&DataFrame, LoadProgress) -> bool,
+) -> Result<TableStruct, DavError> {
+    let file_path = decompressed_path(&file_path)?;
+    match detect_format(&file_path)? {
+        FileFormat::Csv => read_csv(file_path, options, on_batch),
+        FileFormat::Parquet => read_parquet(file_path, on_batch),
+        FileFormat::Ipc => read_ipc(file_path, on_batch),
+        FileFormat::Avro => read_avro(file_path),
+        FileFormat::Json => read_json(file_path),
+        FileFormat::Workbook(kind) => {
+            let (df, errors) = read_excel(file_path, kind, sheet, options)?;
+            Ok(TableStruct::new(df).with_errors(errors))
+        }
+        FileFormat::Sqlite => Ok(TableStruct::new(read_sqlite(&file_path, sheet)?)),
+        // ! Archives are opened through their entries
+        FileFormat::Archive => Err(DavError::UnsupportedFormat("zip".to_string())),
     }
 }
 
-fn read_csv(file_path: String) -> Result<TableStruct, String> {
-    let res = LazyCsvReader::new(file_path).finish();
-    match res {
-        Ok(lf) => {
-            let df = lf.collect().unwrap();
+/// Lazy plan over a file, for the formats that have a scanning reader. Rows
+/// are then only read as far as the plan needs them.
+pub fn scan_file(file_path: &str, options: &ImportOptions) -> Result<Option<LazyFrame>, DavError> {
+    let file_path = decompressed_path(file_path)?;
+    let source = match detect_format(&file_path)? {
+        FileFormat::Csv if !needs_decoding(&options.csv) => {
+            let delimiter = csv_delimiter(&file_path, &options.csv);
+            lazy_csv_reader(&file_path, options, delimiter).finish()?
+        }
+        FileFormat::Parquet => LazyFrame::scan_parquet(file_path, Default::default())?,
+        FileFormat::Ipc => LazyFrame::scan_ipc(file_path, Default::default())?,
+        _ => return Ok(None),
+    };
+    Ok(Some(source))
+}
 
-            let columns = df
-                .get_column_names()
-                .iter()
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>();
-
-            let mut rows: Vec<Vec<String>> = vec![];
-            for row in df.iter() {
-                let mut row_vec: Vec<String> = vec![];
-                for col in row.iter() {
-                    row_vec.push(col.to_string());
+/// Whether `ImportOptions` apply to the file, i.e. it is a text table or a
+/// workbook.
+pub fn has_import_options(file_path: &str) -> bool {
+    matches!(
+        detect_format(file_path),
+        Ok(FileFormat::Csv | FileFormat::Workbook(_))
+    )
+}
+
+pub fn is_text_table(file_path: &str) -> bool {
+    matches!(detect_format(file_path), Ok(FileFormat::Csv))
+}
+
+/// Tells the format from the magic bytes at the start of the file, so a
+/// misnamed or extensionless file still opens, and falls back to the
+/// extension for text formats, which have no magic. Compressed files are
+/// told by what they hold.
+pub fn detect_format(file_path: &str) -> Result<FileFormat, DavError> {
+    let compression = compression_of(file_path)?;
+    let mut sample = vec![];
+    open_decoded(file_path, compression)?
+        .take(DETECT_BYTES as u64)
+        .read_to_end(&mut sample)?;
+    let extension = match compression {
+        Some(_) => file_extension(&decompressed_name(file_path)),
+        None => file_extension(file_path),
+    };
+
+    if sample.starts_with(PARQUET_MAGIC) {
+        return Ok(FileFormat::Parquet);
+    }
+    if sample.starts_with(ARROW_MAGIC) {
+        return Ok(FileFormat::Ipc);
+    }
+    if sample.starts_with(AVRO_MAGIC) {
+        return Ok(FileFormat::Avro);
+    }
+    if sample.starts_with(SQLITE_MAGIC) {
+        return Ok(FileFormat::Sqlite);
+    }
+    if sample.starts_with(OLE_MAGIC) {
+        return Ok(FileFormat::Workbook(WorkbookKind::Xls));
+    }
+    if sample.starts_with(ZIP_MAGIC) {
+        return zip_format(&decompressed_path(file_path)?);
+    }
+
+    // ! A csv whose first cell starts with a bracket is still a csv
+    if !TEXT_EXTENSIONS.contains(&extension.as_str())
+        && matches!(first_char_of(&sample), Some('{' | '['))
+    {
+        return Ok(FileFormat::Json);
+    }
+
+    match extension.as_str() {
+        ext if TEXT_EXTENSIONS.contains(&ext) => Ok(FileFormat::Csv),
+        ext if JSON_EXTENSIONS.contains(&ext) => Ok(FileFormat::Json),
+        ext if IPC_EXTENSIONS.contains(&ext) => Ok(FileFormat::Ipc),
+        "parquet" => Ok(FileFormat::Parquet),
+        "avro" => Ok(FileFormat::Avro),
+        ext if SQLITE_EXTENSIONS.contains(&ext) => Ok(FileFormat::Sqlite),
+        "xlsx" | "xlsm" => Ok(FileFormat::Workbook(WorkbookKind::Xlsx)),
+        "xlsb" => Ok(FileFormat::Workbook(WorkbookKind::Xlsb)),
+        "xls" => Ok(FileFormat::Workbook(WorkbookKind::Xls)),
+        "ods" => Ok(FileFormat::Workbook(WorkbookKind::Ods)),
+        // ! Extensionless text, e.g. an export piped to a file. No NUL byte
+        // ! rules out binary files and UTF-16
+        "" if !sample.is_empty() && !sample.contains(&0) => Ok(FileFormat::Csv),
+        _ => Err(DavError::UnsupportedFormat(extension)),
+    }
+}
+
+/// Workbooks in a zip container are told apart by their entries, any other
+/// zip is an archive to pick a file from.
+fn zip_format(file_path: &str) -> Result<FileFormat, DavError> {
+    let archive = open_archive(file_path)?;
+    let has = |name: &str| archive.file_names().any(|n| n == name);
+
+    if has("xl/workbook.xml") {
+        Ok(FileFormat::Workbook(WorkbookKind::Xlsx))
+    } else if has("xl/workbook.bin") {
+        Ok(FileFormat::Workbook(WorkbookKind::Xlsb))
+    } else if has("content.xml") && has("mimetype") {
+        Ok(FileFormat::Workbook(WorkbookKind::Ods))
+    } else {
+        Ok(FileFormat::Archive)
+    }
+}
+
+fn open_archive(file_path: &str) -> Result<ZipArchive<File>, DavError> {
+    ZipArchive::new(File::open(file_path)?).map_err(|err| DavError::Parse(err.to_string()))
+}
+
+/// Files of a zip archive that can be opened, by their path in the archive.
+pub fn archive_entries(file_path: &str) -> Result<Vec<String>, DavError> {
+    let archive = open_archive(file_path)?;
+    let mut entries = archive
+        .file_names()
+        // ! Resource forks added by the macOS archiver
+        .filter(|name| !name.ends_with('/') && !name.starts_with("__MACOSX/"))
+        .filter(|name| is_supported_name(name))
+        .map(|name| name.to_string())
+        .collect::<Vec<String>>();
+    entries.sort();
+    Ok(entries)
+}
+
+/// Copies an entry of a zip archive to the cache and returns its path there.
+pub fn extract_entry(file_path: &str, entry: &str) -> Result<String, DavError> {
+    let mut archive = open_archive(file_path)?;
+    let mut entry = archive
+        .by_name(entry)
+        .map_err(|err| DavError::Parse(err.to_string()))?;
+
+    // ! Keep the folders of the entry so same-named files don't overwrite each other
+    let relative = entry
+        .enclosed_name()
+        .map(Path::to_path_buf)
+        .ok_or_else(|| DavError::Parse(format!("Unsafe entry path: {}", entry.name())))?;
+    let target = cache_dir(file_path)?.join(relative);
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    io::copy(&mut entry, &mut File::create(&target)?)?;
+
+    Ok(target.to_string_lossy().replace('\\', "/"))
+}
+
+fn is_supported_name(name: &str) -> bool {
+    let extension = file_extension(&decompressed_name(name));
+    [
+        &TEXT_EXTENSIONS[..],
+        &JSON_EXTENSIONS,
+        &IPC_EXTENSIONS,
+        &WORKBOOK_EXTENSIONS,
+        &SQLITE_EXTENSIONS,
+        &["parquet", "avro"],
+    ]
+    .iter()
+    .any(|extensions| extensions.contains(&extension.as_str()))
+}
+
+fn compression_of(file_path: &str) -> Result<Option<Compression>, DavError> {
+    let mut magic = [0u8; 4];
+    let read = File::open(file_path)?.read(&mut magic)?;
+    let magic = &magic[..read];
+
+    Ok(if magic.starts_with(GZIP_MAGIC) {
+        Some(Compression::Gzip)
+    } else if magic.starts_with(ZSTD_MAGIC) {
+        Some(Compression::Zstd)
+    } else if magic.starts_with(BZIP2_MAGIC) {
+        Some(Compression::Bzip2)
+    } else {
+        None
+    })
+}
+
+fn open_decoded(
+    file_path: &str,
+    compression: Option<Compression>,
+) -> Result<Box<dyn Read>, DavError> {
+    decoder(File::open(file_path)?, compression)
+}
+
+fn decoder<'a, R: Read + 'a>(
+    reader: R,
+    compression: Option<Compression>,
+) -> Result<Box<dyn Read + 'a>, DavError> {
+    Ok(match compression {
+        None => Box::new(reader),
+        Some(Compression::Gzip) => Box::new(MultiGzDecoder::new(reader)),
+        Some(Compression::Zstd) => Box::new(zstd::Decoder::new(reader)?),
+        Some(Compression::Bzip2) => Box::new(BzDecoder::new(reader)),
+    })
+}
+
+/// Counts the bytes read through it, i.e. how far a decoder got into the
+/// compressed file.
+struct CountingReader<R> {
+    inner: R,
+    count: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for CountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let read = self.inner.read(buf)?;
+        self.count.set(self.count.get() + read as u64);
+        Ok(read)
+    }
+}
+
+/// Name of the file once decompressed, `data.csv.gz` holds `data.csv`.
+fn decompressed_name(file_path: &str) -> String {
+    let path = Path::new(file_path);
+    let name = path
+        .file_name()
+        .and_then(|name| name.to_str())
+        .unwrap_or_default();
+    match file_extension(file_path).as_str() {
+        ext if COMPRESSED_EXTENSIONS.contains(&ext) => {
+            name[..name.len() - ext.len() - 1].to_string()
+        }
+        _ => name.to_string(),
+    }
+}
+
+/// The readers work on paths, so a compressed file is decompressed once to
+/// the cache and read from there. Other files are read in place.
+pub fn decompressed_path(file_path: &str) -> Result<String, DavError> {
+    decompress_file(file_path, |_| true)
+}
+
+/// Same as [`decompressed_path`], calling `on_progress` with the compressed
+/// bytes read so far while it decompresses. Returning `false` from
+/// `on_progress` cancels the copy.
+pub fn decompress_file(
+    file_path: &str,
+    mut on_progress: impl FnMut(LoadProgress) -> bool,
+) -> Result<String, DavError> {
+    let Some(compression) = compression_of(file_path)? else {
+        return Ok(file_path.to_string());
+    };
+    let target = cache_dir(file_path)?.join(decompressed_name(file_path));
+
+    // ! Reuse the copy unless the file changed since
+    let modified = |path: &Path| fs::metadata(path).and_then(|m| m.modified()).ok();
+    let is_fresh = match (modified(&target), modified(Path::new(file_path))) {
+        (Some(copy), Some(source)) => copy >= source,
+        _ => false,
+    };
+    if !is_fresh {
+        println!("[*] Decompressing {}", file_path);
+        // ! Written aside first, a cancelled copy must not look fresh
+        let partial = target.with_extension("partial");
+        let total_bytes = fs::metadata(file_path)?.len();
+        let read = Rc::new(Cell::new(0));
+        let mut decoded = decoder(
+            CountingReader {
+                inner: File::open(file_path)?,
+                count: read.clone(),
+            },
+            Some(compression),
+        )?;
+        let mut copy = File::create(&partial)?;
+
+        let mut buf = vec![0u8; SAMPLE_BYTES];
+        let mut reported = 0;
+        loop {
+            let len = decoded.read(&mut buf)?;
+            if len == 0 {
+                break;
+            }
+            copy.write_all(&buf[..len])?;
+
+            if read.get() - reported >= PROGRESS_BYTES {
+                reported = read.get();
+                let progress = LoadProgress {
+                    rows: 0,
+                    bytes: reported,
+                    total_bytes,
+                    fraction: Some((reported as f32 / total_bytes.max(1) as f32).min(1.)),
+                };
+                if !on_progress(progress) {
+                    drop(copy);
+                    fs::remove_file(&partial)?;
+                    return Err(DavError::Cancelled);
                 }
-                rows.push(row_vec);
             }
+        }
+        drop(copy);
+        fs::rename(&partial, &target)?;
+    }
 
-            Ok(TableStruct { columns, rows })
+    Ok(target.to_string_lossy().replace('\\', "/"))
+}
+
+/// Cache folder for the files extracted from `file_path`, one per source so
+/// two `data.csv.gz` from different folders do not clash.
+fn cache_dir(file_path: &str) -> Result<PathBuf, DavError> {
+    let dir = cache_dir_of(file_path);
+    fs::create_dir_all(&dir)?;
+    Ok(dir)
+}
+
+fn cache_dir_of(file_path: &str) -> PathBuf {
+    let mut hasher = DefaultHasher::new();
+    file_path.hash(&mut hasher);
+    cache_root().join(format!("{:016x}", hasher.finish()))
+}
+
+fn cache_root() -> PathBuf {
+    env::temp_dir().join("DaV")
+}
+
+/// Whether the file is a copy extracted from an archive.
+pub fn is_extracted(file_path: &str) -> bool {
+    Path::new(file_path).starts_with(cache_root())
+}
+
+/// Deletes what was decompressed for a file, and the file itself when it was
+/// extracted from an archive, once nothing shows it anymore.
+pub fn remove_cached(file_path: &str) {
+    let dir = cache_dir_of(file_path);
+    if dir.exists() {
+        if let Err(err) = fs::remove_dir_all(&dir) {
+            println!("[!] Cannot remove {}: {}", dir.display(), err);
+        }
+    }
+    if !is_extracted(file_path) {
+        return;
+    }
+
+    if let Err(err) = fs::remove_file(file_path) {
+        println!("[!] Cannot remove {}: {}", file_path, err);
+    }
+    // ! Then the folders of the entry, as long as they are empty
+    let root = cache_root();
+    let mut parent = Path::new(file_path).parent();
+    while let Some(dir) = parent.filter(|dir| *dir != root && dir.starts_with(&root)) {
+        if fs::remove_dir(dir).is_err() {
+            break;
+        }
+        parent = dir.parent();
+    }
+}
+
+/// Icon of a file format, told by the extension alone so it is cheap to call
+/// every frame.
+pub fn format_icon(file_path: &str) -> &'static str {
+    match file_extension(file_path).as_str() {
+        ext if TEXT_EXTENSIONS.contains(&ext) => egui_phosphor::regular::FILE_CSV,
+        ext if JSON_EXTENSIONS.contains(&ext) => egui_phosphor::regular::BRACKETS_CURLY,
+        ext if WORKBOOK_EXTENSIONS.contains(&ext) => egui_phosphor::regular::FILE_XLS,
+        ext if SQLITE_EXTENSIONS.contains(&ext) => egui_phosphor::regular::DATABASE,
+        ext if COMPRESSED_EXTENSIONS.contains(&ext) || ext == "zip" => {
+            egui_phosphor::regular::FILE_ZIP
+        }
+        _ => egui_phosphor::regular::TABLE,
+    }
+}
+
+/// First `rows` rows of a file read with `options`, for the import dialog.
+pub fn preview_file(
+    file_path: String,
+    sheet: Option<&str>,
+    options: &ImportOptions,
+    rows: usize,
+) -> Result<DataFrame, DavError> {
+    let file_path = decompressed_path(&file_path)?;
+    match detect_format(&file_path)? {
+        FileFormat::Csv => {
+            let delimiter = csv_delimiter(&file_path, &options.csv);
+            let df = if needs_decoding(&options.csv) {
+                let text = decode_file(&file_path, &options.csv)?;
+                configure_csv(CsvReader::new(Cursor::new(text)), options, delimiter)
+                    .with_n_rows(Some(rows))
+                    .finish()?
+            } else {
+                configure_csv(CsvReader::from_path(&file_path)?, options, delimiter)
+                    .with_n_rows(Some(rows))
+                    .finish()?
+            };
+            Ok(df)
+        }
+        FileFormat::Workbook(kind) => {
+            let (df, _) = read_excel(file_path, kind, sheet, options)?;
+            Ok(df.head(Some(rows)))
         }
-        Err(err) => Err(err.to_string()),
+        FileFormat::Parquet
+        | FileFormat::Ipc
+        | FileFormat::Avro
+        | FileFormat::Json
+        | FileFormat::Sqlite
+        | FileFormat::Archive => Err(DavError::UnsupportedFormat(file_extension(&file_path))),
     }
 }
 
-fn read_excel(file_path: String) -> Result<TableStruct, String> {
-    let mut workbook: Xlsx<_> = open_workbook(file_path).expect("Cannot open file");
+/// Sheet names of a workbook, or tables and views of a database, empty for
+/// the other formats.
+pub fn list_sheets(file_path: &str) -> Result<Vec<String>, DavError> {
+    match detect_format(file_path)? {
+        FileFormat::Workbook(kind) => {
+            let file_path = decompressed_path(file_path)?;
+            Ok(open_workbook_as(&file_path, kind)?.sheet_names().to_vec())
+        }
+        FileFormat::Sqlite => sqlite_tables(&decompressed_path(file_path)?),
+        _ => Ok(vec![]),
+    }
+}
+
+/// Opens a workbook as `kind` whatever its extension, `open_workbook_auto`
+/// goes by the extension.
+fn open_workbook_as(
+    file_path: &str,
+    kind: WorkbookKind,
+) -> Result<Sheets<BufReader<File>>, CalamineError> {
+    Ok(match kind {
+        WorkbookKind::Xlsx => Sheets::Xlsx(open_workbook::<Xlsx<_>, _>(file_path)?),
+        WorkbookKind::Xlsb => Sheets::Xlsb(open_workbook::<Xlsb<_>, _>(file_path)?),
+        WorkbookKind::Xls => Sheets::Xls(open_workbook::<Xls<_>, _>(file_path)?),
+        WorkbookKind::Ods => Sheets::Ods(open_workbook::<Ods<_>, _>(file_path)?),
+    })
+}
 
-    let res = workbook.worksheet_range_at(0);
+fn file_extension(file_path: &str) -> String {
+    Path::new(file_path)
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .unwrap_or_default()
+        .to_lowercase()
+}
+
+fn configure_csv<'a, R: MmapBytesReader + 'a>(
+    reader: CsvReader<'a, R>,
+    options: &ImportOptions,
+    delimiter: u8,
+) -> CsvReader<'a, R> {
+    reader
+        .with_skip_rows(options.header_row.unwrap_or(0))
+        .has_header(options.has_header)
+        .with_skip_rows_after_header(options.skip_rows)
+        .with_delimiter(delimiter)
+        .with_quote_char(options.csv.quote)
+        .with_comment_char(options.csv.comment)
+        .with_encoding(csv_encoding(options.csv.encoding))
+        .infer_schema(options.csv.infer_schema_length)
+}
+
+fn lazy_csv_reader<'a>(
+    file_path: &str,
+    options: &ImportOptions,
+    delimiter: u8,
+) -> LazyCsvReader<'a> {
+    LazyCsvReader::new(file_path)
+        .with_skip_rows(options.header_row.unwrap_or(0))
+        .has_header(options.has_header)
+        .with_skip_rows_after_header(options.skip_rows)
+        .with_delimiter(delimiter)
+        .with_quote_char(options.csv.quote)
+        .with_comment_char(options.csv.comment)
+        .with_encoding(csv_encoding(options.csv.encoding))
+        .with_infer_schema_length(options.csv.infer_schema_length)
+}
 
-    match res {
-        None => Err("No sheet found".to_string()),
-        Some(range) => {
-            let range = range.unwrap();
+fn csv_encoding(encoding: TextEncoding) -> CsvEncoding {
+    match encoding {
+        TextEncoding::Utf8Lossy => CsvEncoding::LossyUtf8,
+        _ => CsvEncoding::Utf8,
+    }
+}
+
+/// Polars only parses UTF-8 and doubled quotes, other encodings and escape
+/// characters are converted in memory first.
+fn needs_decoding(csv: &CsvOptions) -> bool {
+    csv.escape.is_some() || !matches!(csv.encoding, TextEncoding::Utf8 | TextEncoding::Utf8Lossy)
+}
+
+/// Reads a whole text file as UTF-8 with `\"`-style escapes turned into
+/// doubled quotes.
+fn decode_file(file_path: &str, csv: &CsvOptions) -> Result<Vec<u8>, DavError> {
+    let bytes = fs::read(file_path)?;
+    let text = match csv.encoding {
+        TextEncoding::Utf8 => String::from_utf8(bytes)
+            .map_err(|err| DavError::Parse(format!("{}, try another encoding", err)))?,
+        encoding => decode_text(&bytes, encoding),
+    };
+
+    let text = match (csv.escape, csv.quote) {
+        (Some(escape), Some(quote)) => unescape_quotes(&text, escape as char, quote as char),
+        _ => text,
+    };
+    Ok(text.into_bytes())
+}
+
+/// Decodes text, replacing invalid sequences. A byte order mark overrides
+/// `encoding`.
+fn decode_text(bytes: &[u8], encoding: TextEncoding) -> String {
+    let encoding = match encoding {
+        TextEncoding::Utf8 | TextEncoding::Utf8Lossy => encoding_rs::UTF_8,
+        TextEncoding::Latin1 => encoding_rs::WINDOWS_1252,
+        TextEncoding::Utf16Le => encoding_rs::UTF_16LE,
+        TextEncoding::Utf16Be => encoding_rs::UTF_16BE,
+    };
+    let (text, _, _) = encoding.decode(bytes);
+    text.into_owned()
+}
 
-            let columns = range
-                .rows()
-                .next()
-                .unwrap()
+fn unescape_quotes(text: &str, escape: char, quote: char) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (c, chars.peek()) {
+            (c, Some(&next)) if c == escape && next == quote => {
+                out.push(quote);
+                out.push(quote);
+                chars.next();
+            }
+            (c, Some(&next)) if c == escape && next == escape => {
+                out.push(escape);
+                chars.next();
+            }
+            (c, _) => out.push(c),
+        }
+    }
+    out
+}
+
+/// Delimiter picked by the user, else sniffed from the first lines, else the
+/// usual one for the extension.
+fn csv_delimiter(file_path: &str, csv: &CsvOptions) -> u8 {
+    if let Some(delimiter) = csv.delimiter {
+        return delimiter;
+    }
+
+    let mut sample = vec![0u8; SAMPLE_BYTES];
+    let sniffed = File::open(file_path)
+        .and_then(|mut file| file.read(&mut sample))
+        .ok()
+        .and_then(|read| {
+            let text = decode_text(&sample[..read], csv.encoding);
+            sniff_delimiter(&text, csv.quote, csv.comment)
+        });
+
+    sniffed.unwrap_or(match file_extension(file_path).as_str() {
+        "tsv" => b'\t',
+        "psv" => b'|',
+        _ => b',',
+    })
+}
+
+/// The candidate found the same number of times on most lines wins, ties go
+/// to the earlier one in `DELIMITERS`. A delimiter missing from the first
+/// line is never picked.
+fn sniff_delimiter(text: &str, quote: Option<u8>, comment: Option<u8>) -> Option<u8> {
+    let mut lines = text
+        .lines()
+        .filter(|l| !l.trim().is_empty())
+        .filter(|l| comment.map_or(true, |c| !l.starts_with(c as char)))
+        .take(SNIFF_LINES + 1)
+        .collect::<Vec<&str>>();
+    // ! The sample may end in the middle of a line
+    if lines.len() > SNIFF_LINES {
+        lines.pop();
+    }
+
+    DELIMITERS
+        .iter()
+        .enumerate()
+        .filter_map(|(idx, &delimiter)| {
+            let counts = lines
                 .iter()
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>();
-
-            let mut rows: Vec<Vec<String>> = vec![];
-            for (idx, r) in range.rows().skip(1).enumerate() {
-                rows.push(vec![]);
-                for (_, c) in r.iter().enumerate() {
-                    match *c {
-                        DataType::Empty => {
-                            rows[idx].push("null".to_string());
-                        }
-                        DataType::String(ref s)
-                        | DataType::DateTimeIso(ref s)
-                        | DataType::DurationIso(ref s) => {
-                            rows[idx].push(s.to_string());
-                        }
-                        DataType::Float(ref f_type)
-                        | DataType::DateTime(ref f_type)
-                        | DataType::Duration(ref f_type) => {
-                            rows[idx].push(f_type.to_string());
-                        }
-                        DataType::Int(ref i) => {
-                            rows[idx].push(i.to_string());
-                        }
-                        DataType::Error(ref e) => {
-                            return Err(e.to_string());
-                        }
-                        DataType::Bool(ref b) => {
-                            rows[idx].push(b.to_string());
-                        }
-                    };
-                }
+                .map(|l| count_unquoted(l, delimiter, quote))
+                .collect::<Vec<usize>>();
+            let first = *counts.first()?;
+            if first == 0 {
+                return None;
             }
+            let consistent = counts.iter().filter(|c| **c == first).count();
+            Some((consistent, first, Reverse(idx), delimiter))
+        })
+        .max_by_key(|(consistent, count, idx, _)| (*consistent, *count, *idx))
+        .map(|(_, _, _, delimiter)| delimiter)
+}
 
-            Ok(TableStruct { columns, rows })
+fn count_unquoted(line: &str, delimiter: u8, quote: Option<u8>) -> usize {
+    let mut in_quotes = false;
+    let mut count = 0;
+    for b in line.bytes() {
+        if Some(b) == quote {
+            in_quotes = !in_quotes;
+        } else if b == delimiter && !in_quotes {
+            count += 1;
         }
     }
+    count
 }
 
-fn read_parquet(file_path: String) -> Result<TableStruct, String> {
-    let res = LazyFrame::scan_parquet(file_path, Default::default());
-    match res {
-        Ok(lf) => {
-            let df = lf.collect().unwrap();
+fn read_csv(
+    file_path: String,
+    options: &ImportOptions,
+    mut on_batch: impl FnMut(&DataFrame, LoadProgress) -> bool,
+) -> Result<TableStruct, DavError> {
+    let delimiter = csv_delimiter(&file_path, &options.csv);
+    if needs_decoding(&options.csv) {
+        let text = decode_file(&file_path, &options.csv)?;
+        let df = configure_csv(CsvReader::new(Cursor::new(text)), options, delimiter).finish()?;
+        return Ok(TableStruct::new(df));
+    }
+
+    let total_bytes = fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
+    let row_bytes = estimate_row_bytes(&file_path);
 
-            let columns = df
-                .get_column_names()
-                .iter()
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>();
-
-            let mut rows: Vec<Vec<String>> = vec![];
-            for row in df.iter() {
-                let mut row_vec: Vec<String> = vec![];
-                for col in row.iter() {
-                    row_vec.push(col.to_string());
+    let mut reader = configure_csv(CsvReader::from_path(&file_path)?, options, delimiter)
+        .with_chunk_size(BATCH_ROWS);
+    let mut batched = reader.batched_borrowed_read()?;
+
+    let mut df: Option<DataFrame> = None;
+    while let Some(batches) = batched.next_batches(1)? {
+        for batch in batches {
+            match &mut df {
+                Some(df) => {
+                    df.vstack_mut(&batch)?;
                 }
-                rows.push(row_vec);
+                None => df = Some(batch),
             }
+        }
 
-            Ok(TableStruct { columns, rows })
+        if let Some(df) = &df {
+            // ! Text has no row index, so progress is estimated from the
+            // ! average line length
+            let bytes = row_bytes
+                .map(|b| (b * df.height() as f64) as u64)
+                .unwrap_or(0)
+                .min(total_bytes);
+            let progress = LoadProgress {
+                rows: df.height(),
+                bytes,
+                total_bytes,
+                fraction: row_bytes
+                    .filter(|_| total_bytes > 0)
+                    .map(|_| (bytes as f32 / total_bytes as f32).min(0.99)),
+            };
+            if !on_batch(df, progress) {
+                return Err(DavError::Cancelled);
+            }
         }
-        Err(err) => Err(err.to_string()),
     }
+
+    let source = lazy_csv_reader(&file_path, options, delimiter).finish()?;
+    match df {
+        Some(mut df) => {
+            df.as_single_chunk_par();
+            Ok(TableStruct::with_source(df, source))
+        }
+        None => collect_lazy(source),
+    }
+}
+
+/// Avro has no lazy reader, the whole file is read at once.
+fn read_avro(file_path: String) -> Result<TableStruct, DavError> {
+    let df = AvroReader::new(File::open(&file_path)?).finish()?;
+    Ok(TableStruct::new(df))
+}
+
+fn open_sqlite(file_path: &str) -> Result<Connection, DavError> {
+    Ok(Connection::open_with_flags(
+        file_path,
+        OpenFlags::SQLITE_OPEN_READ_ONLY | OpenFlags::SQLITE_OPEN_NO_MUTEX,
+    )?)
+}
+
+/// Tables, then views, of a SQLite database. Internal tables are left out.
+fn sqlite_tables(file_path: &str) -> Result<Vec<String>, DavError> {
+    let conn = open_sqlite(file_path)?;
+    let mut stmt = conn.prepare(
+        "SELECT name FROM sqlite_master \
+         WHERE type IN ('table', 'view') AND name NOT LIKE 'sqlite_%' \
+         ORDER BY type, name",
+    )?;
+    let names = stmt
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<Result<Vec<String>, _>>()?;
+    Ok(names)
+}
+
+/// Reads a table or view, or the first table when `table` is `None`.
+fn read_sqlite(file_path: &str, table: Option<&str>) -> Result<DataFrame, DavError> {
+    let table = match table {
+        Some(table) => table.to_string(),
+        None => sqlite_tables(file_path)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| DavError::Parse("The database has no tables".to_string()))?,
+    };
+    query_sqlite(
+        file_path,
+        &format!("SELECT * FROM \"{}\"", table.replace('"', "\"\"")),
+    )
 }
 
-fn read_json(file_path: String) -> Result<TableStruct, String> {
-    let cursor = Cursor::new(fs::read_to_string(file_path).unwrap());
-    let res = JsonReader::new(cursor).finish();
-    match res {
-        Ok(df) => {
-            let columns = df
-                .get_column_names()
+/// Runs a query against a SQLite database, for its tables and the SQL
+/// console.
+pub fn query_sqlite(file_path: &str, query: &str) -> Result<DataFrame, DavError> {
+    let file_path = decompressed_path(file_path)?;
+    let conn = open_sqlite(&file_path)?;
+    let mut stmt = conn.prepare(query)?;
+
+    let header = stmt
+        .column_names()
+        .into_iter()
+        .map(|name| name.to_string())
+        .collect::<Vec<String>>();
+    let names = unique_column_names(&header);
+
+    let mut columns: Vec<Vec<SqlValue>> = vec![vec![]; names.len()];
+    let mut rows = stmt.query([])?;
+    while let Some(row) = rows.next()? {
+        for (idx, cells) in columns.iter_mut().enumerate() {
+            cells.push(row.get::<_, SqlValue>(idx)?);
+        }
+    }
+
+    let series = names
+        .iter()
+        .zip(columns.iter())
+        .map(|(name, cells)| sqlite_column_to_series(name, cells))
+        .collect::<Vec<Series>>();
+    Ok(DataFrame::new(series)?)
+}
+
+/// SQLite columns have no fixed type, the column gets the narrowest type
+/// that holds all its values and falls back to text.
+fn sqlite_column_to_series(name: &str, cells: &[SqlValue]) -> Series {
+    let is = |pred: fn(&SqlValue) -> bool| {
+        cells
+            .iter()
+            .all(|cell| matches!(cell, SqlValue::Null) || pred(cell))
+    };
+
+    if is(|cell| matches!(cell, SqlValue::Integer(_))) {
+        let values = cells
+            .iter()
+            .map(|cell| match cell {
+                SqlValue::Integer(v) => Some(*v),
+                _ => None,
+            })
+            .collect::<Vec<Option<i64>>>();
+        Series::new(name, values)
+    } else if is(|cell| matches!(cell, SqlValue::Integer(_) | SqlValue::Real(_))) {
+        let values = cells
+            .iter()
+            .map(|cell| match cell {
+                SqlValue::Integer(v) => Some(*v as f64),
+                SqlValue::Real(v) => Some(*v),
+                _ => None,
+            })
+            .collect::<Vec<Option<f64>>>();
+        Series::new(name, values)
+    } else {
+        let values = cells
+            .iter()
+            .map(|cell| match cell {
+                SqlValue::Null => None,
+                SqlValue::Integer(v) => Some(v.to_string()),
+                SqlValue::Real(v) => Some(v.to_string()),
+                SqlValue::Text(v) => Some(v.clone()),
+                SqlValue::Blob(v) => Some(format!("<{} bytes>", v.len())),
+            })
+            .collect::<Vec<Option<String>>>();
+        Series::new(name, values)
+    }
+}
+
+/// Average line length of the first few KiB of a text file.
+fn estimate_row_bytes(file_path: &str) -> Option<f64> {
+    let mut buf = vec![0u8; 64 * 1024];
+    let read = File::open(file_path).ok()?.read(&mut buf).ok()?;
+    let lines = buf[..read].iter().filter(|b| **b == b'\n').count();
+    if lines == 0 {
+        None
+    } else {
+        Some(read as f64 / lines as f64)
+    }
+}
+
+fn read_excel(
+    file_path: String,
+    kind: WorkbookKind,
+    sheet: Option<&str>,
+    options: &ImportOptions,
+) -> Result<(DataFrame, CellErrors), DavError> {
+    let date_system = DateSystem::of_workbook(&file_path);
+    let mut workbook = open_workbook_as(&file_path, kind)?;
+
+    let sheet = match sheet {
+        Some(sheet) => sheet.to_string(),
+        None => workbook
+            .sheet_names()
+            .first()
+            .cloned()
+            .ok_or_else(|| DavError::Parse("The workbook has no sheets".to_string()))?,
+    };
+    let range = workbook
+        .worksheet_range(&sheet)
+        .ok_or_else(|| DavError::SheetMissing(sheet.clone()))??;
+
+    // ! Rows above the header are titles and banners
+    let header_row = options
+        .header_row
+        .unwrap_or_else(|| detect_header_row(&range));
+    let mut rows = range.rows().skip(header_row);
+
+    let names = if options.has_header {
+        let Some(header) = rows.next() else {
+            return Ok((DataFrame::default(), CellErrors::new()));
+        };
+        let header = header
+            .iter()
+            .map(|s| s.to_string())
+            .collect::<Vec<String>>();
+        unique_column_names(&header)
+    } else {
+        (1..=range.width())
+            .map(|idx| format!("column_{}", idx))
+            .collect::<Vec<String>>()
+    };
+    let rows = rows.skip(options.skip_rows).collect::<Vec<&[DataType]>>();
+
+    let mut series: Vec<Series> = vec![];
+    let mut errors = CellErrors::new();
+    for (idx, name) in names.iter().enumerate() {
+        let cells = rows
+            .iter()
+            .map(|r| r.get(idx).unwrap_or(&DataType::Empty))
+            .collect::<Vec<&DataType>>();
+        series.push(excel_column_to_series(name, &cells, date_system)?);
+
+        let column_errors = cells
+            .iter()
+            .enumerate()
+            .filter_map(|(row, c)| match c {
+                DataType::Error(err) => Some((row, err.to_string())),
+                _ => None,
+            })
+            .collect::<BTreeMap<usize, String>>();
+        if !column_errors.is_empty() {
+            errors.insert(name.clone(), column_errors);
+        }
+    }
+
+    Ok((DataFrame::new(series)?, errors))
+}
+
+/// Guesses the header row of a sheet: the first of the top rows that reaches
+/// the last used column and only holds text, blanks aside. Falls back to the
+/// first row.
+fn detect_header_row(range: &Range<DataType>) -> usize {
+    let rows = range
+        .rows()
+        .take(HEADER_SCAN_ROWS)
+        .map(|r| {
+            // ! Width up to the last used cell, a header may leave some blank
+            let width = r
                 .iter()
-                .map(|s| s.to_string())
-                .collect::<Vec<String>>();
-
-            let mut rows: Vec<Vec<String>> = vec![];
-            for row in df.iter() {
-                let mut row_vec: Vec<String> = vec![];
-                for col in row.iter() {
-                    row_vec.push(col.to_string());
-                }
-                rows.push(row_vec);
+                .rposition(|c| !matches!(c, DataType::Empty))
+                .map_or(0, |idx| idx + 1);
+            (width, &r[..width])
+        })
+        .collect::<Vec<_>>();
+    let width = rows.iter().map(|(width, _)| *width).max().unwrap_or(0);
+
+    rows.iter()
+        .position(|(w, cells)| {
+            *w == width
+                && cells
+                    .iter()
+                    .all(|c| matches!(c, DataType::String(_) | DataType::Empty))
+        })
+        .unwrap_or(0)
+}
+
+/// Builds a typed column out of Excel cells: integers, floats, dates,
+/// durations and booleans keep their type when the whole column agrees,
+/// anything else falls back to text. Error cells (`#DIV/0!`, `#N/A`, ...) are
+/// null like empty ones, their codes are collected apart by `read_excel`.
+fn excel_column_to_series(
+    name: &str,
+    cells: &[&DataType],
+    date_system: DateSystem,
+) -> Result<Series, DavError> {
+    let values = cells
+        .iter()
+        .filter(|c| !matches!(c, DataType::Empty | DataType::Error(_)))
+        .collect::<Vec<_>>();
+
+    if values.iter().all(|c| matches!(c, DataType::Int(_))) {
+        let col = cells
+            .iter()
+            .map(|c| match c {
+                DataType::Int(i) => Some(*i),
+                _ => None,
+            })
+            .collect::<Vec<Option<i64>>>();
+        return Ok(Series::new(name, col));
+    }
+
+    if values
+        .iter()
+        .all(|c| matches!(c, DataType::Int(_) | DataType::Float(_)))
+    {
+        let col = cells
+            .iter()
+            .map(|c| match c {
+                DataType::Int(i) => Some(*i as f64),
+                DataType::Float(f) => Some(*f),
+                _ => None,
+            })
+            .collect::<Vec<Option<f64>>>();
+        return Ok(Series::new(name, col));
+    }
+
+    let datetimes = cells
+        .iter()
+        .map(|c| match c {
+            DataType::DateTime(serial) => date_system.to_datetime(*serial),
+            DataType::DateTimeIso(text) => parse_iso_datetime(text),
+            _ => None,
+        })
+        .collect::<Vec<Option<NaiveDateTime>>>();
+    if datetimes.iter().filter(|d| d.is_some()).count() == values.len() {
+        let millis = datetimes
+            .iter()
+            .map(|d| d.map(|d| d.timestamp_millis()))
+            .collect::<Vec<Option<i64>>>();
+        return Ok(Series::new(name, millis).cast(&DType::Datetime(TimeUnit::Milliseconds, None))?);
+    }
+
+    let durations = cells
+        .iter()
+        .map(|c| match c {
+            DataType::Duration(days) => Some((days * MILLIS_PER_DAY).round() as i64),
+            DataType::DurationIso(text) => parse_iso_duration(text),
+            _ => None,
+        })
+        .collect::<Vec<Option<i64>>>();
+    if durations.iter().filter(|d| d.is_some()).count() == values.len() {
+        return Ok(Series::new(name, durations).cast(&DType::Duration(TimeUnit::Milliseconds))?);
+    }
+
+    if values.iter().all(|c| matches!(c, DataType::Bool(_))) {
+        let col = cells
+            .iter()
+            .map(|c| match c {
+                DataType::Bool(b) => Some(*b),
+                _ => None,
+            })
+            .collect::<Vec<Option<bool>>>();
+        return Ok(Series::new(name, col));
+    }
+
+    let col = cells
+        .iter()
+        .zip(datetimes.iter().zip(durations.iter()))
+        .map(|(c, (datetime, duration))| match (c, datetime, duration) {
+            (DataType::Empty | DataType::Error(_), _, _) => None,
+            (_, Some(datetime), _) => Some(format_datetime(datetime)),
+            (_, _, Some(duration)) => Some(format_duration(*duration)),
+            (other, _, _) => Some(other.to_string()),
+        })
+        .collect::<Vec<Option<String>>>();
+    Ok(Series::new(name, col))
+}
+
+const MILLIS_PER_DAY: f64 = 86_400_000.;
+
+/// Day Excel serial dates count from, set per workbook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum DateSystem {
+    /// Windows Excel, serial 1 is 1900-01-01
+    Excel1900,
+    /// Old Mac Excel, serial 0 is 1904-01-01
+    Excel1904,
+}
+
+impl DateSystem {
+    /// Reads the `date1904` flag of xlsx/xlsm workbooks. calamine does not
+    /// expose it, and the other formats are assumed to use 1900.
+    fn of_workbook(file_path: &str) -> Self {
+        let is_1904 = File::open(file_path)
+            .ok()
+            .and_then(|file| ZipArchive::new(file).ok())
+            .and_then(|mut archive| {
+                let mut xml = String::new();
+                archive
+                    .by_name("xl/workbook.xml")
+                    .ok()?
+                    .read_to_string(&mut xml)
+                    .ok()?;
+                Some(xml)
+            })
+            .and_then(|xml| {
+                let start = xml.find("workbookPr ")?;
+                let end = xml[start..].find('>')? + start;
+                let props = &xml[start..end];
+                Some(props.contains("date1904=\"1\"") || props.contains("date1904=\"true\""))
+            })
+            .unwrap_or(false);
+
+        if is_1904 {
+            DateSystem::Excel1904
+        } else {
+            DateSystem::Excel1900
+        }
+    }
+
+    fn to_datetime(self, serial: f64) -> Option<NaiveDateTime> {
+        let (epoch, days) = match self {
+            // ! Excel treats 1900 as a leap year, serials before the phantom
+            // ! 1900-02-29 (60) are one day off
+            DateSystem::Excel1900 if serial < 60. => {
+                (NaiveDate::from_ymd_opt(1899, 12, 31)?, serial)
+            }
+            DateSystem::Excel1900 => (NaiveDate::from_ymd_opt(1899, 12, 30)?, serial),
+            DateSystem::Excel1904 => (NaiveDate::from_ymd_opt(1904, 1, 1)?, serial),
+        };
+        let millis = (days * MILLIS_PER_DAY).round() as i64;
+        epoch
+            .and_hms_opt(0, 0, 0)?
+            .checked_add_signed(chrono::Duration::milliseconds(millis))
+    }
+}
+
+fn parse_iso_datetime(text: &str) -> Option<NaiveDateTime> {
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .or_else(|| {
+            NaiveDate::parse_from_str(text, "%Y-%m-%d")
+                .ok()
+                .and_then(|date| date.and_hms_opt(0, 0, 0))
+        })
+}
+
+/// Parses the `PT12H30M15S` durations of OpenDocument files into milliseconds.
+fn parse_iso_duration(text: &str) -> Option<i64> {
+    let (sign, text) = match text.strip_prefix('-') {
+        Some(text) => (-1., text),
+        None => (1., text),
+    };
+    let text = text.strip_prefix('P')?;
+
+    let mut millis = 0.;
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in text.chars() {
+        match c {
+            'T' => in_time = true,
+            '0'..='9' | '.' => number.push(c),
+            unit => {
+                let value = number.parse::<f64>().ok()?;
+                number.clear();
+                millis += value
+                    * match (unit, in_time) {
+                        ('D', false) => MILLIS_PER_DAY,
+                        ('H', true) => 3_600_000.,
+                        ('M', true) => 60_000.,
+                        ('S', true) => 1_000.,
+                        _ => return None,
+                    };
+            }
+        }
+    }
+
+    if number.is_empty() {
+        Some((sign * millis).round() as i64)
+    } else {
+        None
+    }
+}
+
+fn format_datetime(datetime: &NaiveDateTime) -> String {
+    if datetime.num_seconds_from_midnight() == 0 {
+        datetime.format("%Y-%m-%d").to_string()
+    } else {
+        datetime.format("%Y-%m-%d %H:%M:%S").to_string()
+    }
+}
+
+/// Polars refuses duplicated or empty column names, which spreadsheets allow.
+fn unique_column_names(header: &[String]) -> Vec<String> {
+    let mut names: Vec<String> = vec![];
+    for (idx, name) in header.iter().enumerate() {
+        let base = if name.trim().is_empty() {
+            format!("column_{}", idx + 1)
+        } else {
+            name.trim().to_string()
+        };
+
+        let mut candidate = base.clone();
+        let mut suffix = 1;
+        while names.contains(&candidate) {
+            candidate = format!("{}_{}", base, suffix);
+            suffix += 1;
+        }
+        names.push(candidate);
+    }
+    names
+}
+
+fn read_parquet(
+    file_path: String,
+    on_batch: impl FnMut(&DataFrame, LoadProgress) -> bool,
+) -> Result<TableStruct, DavError> {
+    let total_bytes = fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
+    let total_rows = ParquetReader::new(File::open(&file_path)?).num_rows()?;
+
+    let source = LazyFrame::scan_parquet(file_path, Default::default())?;
+    read_lazy_batches(source, total_rows, total_bytes, on_batch)
+}
+
+/// Scans an Arrow IPC file, which is memory mapped so slices are as cheap as
+/// parquet row groups.
+fn read_ipc(
+    file_path: String,
+    on_batch: impl FnMut(&DataFrame, LoadProgress) -> bool,
+) -> Result<TableStruct, DavError> {
+    let total_bytes = fs::metadata(&file_path).map(|m| m.len()).unwrap_or(0);
+
+    let source = LazyFrame::scan_ipc(file_path, Default::default())?;
+    let total_rows = source
+        .clone()
+        .select([count()])
+        .collect()?
+        .get_columns()
+        .first()
+        .and_then(|s| s.get(0).ok())
+        .and_then(|v| v.extract::<usize>())
+        .unwrap_or(0);
+
+    read_lazy_batches(source, total_rows, total_bytes, on_batch)
+}
+
+/// Collects `source` in slices of `BATCH_ROWS`, reporting the rows read so
+/// far after each one.
+fn read_lazy_batches(
+    source: LazyFrame,
+    total_rows: usize,
+    total_bytes: u64,
+    mut on_batch: impl FnMut(&DataFrame, LoadProgress) -> bool,
+) -> Result<TableStruct, DavError> {
+    // ! Slices are pushed down to the row groups, so each batch only reads
+    // ! the part of the file it needs
+    let mut df: Option<DataFrame> = None;
+    let mut offset = 0;
+    while offset < total_rows {
+        let batch = source
+            .clone()
+            .slice(offset as i64, BATCH_ROWS as IdxSize)
+            .collect()?;
+        offset += batch.height().max(1);
+
+        match &mut df {
+            Some(df) => {
+                df.vstack_mut(&batch)?;
             }
+            None => df = Some(batch),
+        }
+
+        if let Some(df) = &df {
+            let fraction = df.height() as f32 / total_rows as f32;
+            let progress = LoadProgress {
+                rows: df.height(),
+                bytes: (total_bytes as f32 * fraction) as u64,
+                total_bytes,
+                fraction: Some(fraction),
+            };
+            if !on_batch(df, progress) {
+                return Err(DavError::Cancelled);
+            }
+        }
+    }
+
+    match df {
+        Some(mut df) => {
+            df.as_single_chunk_par();
+            Ok(TableStruct::with_source(df, source))
+        }
+        None => collect_lazy(source),
+    }
+}
+
+/// Reads an array of objects, or one object per line for JSON Lines. Nested
+/// objects are flattened into dotted columns.
+fn read_json(file_path: String) -> Result<TableStruct, DavError> {
+    let is_lines = match file_extension(&file_path).as_str() {
+        "jsonl" | "ndjson" => true,
+        // ! A `.json` file holding one object per line is JSON Lines too
+        _ => first_line_is_object(&file_path)?,
+    };
+
+    let format = if is_lines {
+        JsonFormat::JsonLines
+    } else {
+        JsonFormat::Json
+    };
+    let df = JsonReader::new(File::open(&file_path)?)
+        .with_json_format(format)
+        .finish()?;
+
+    Ok(TableStruct::new(flatten_structs(df)?))
+}
+
+/// Whether the first non-blank line of a text file is a whole JSON object on
+/// its own, which a pretty-printed `{` document is not.
+fn first_line_is_object(file_path: &str) -> Result<bool, DavError> {
+    let mut reader = BufReader::new(File::open(file_path)?.take(SAMPLE_BYTES as u64));
+    let mut line = String::new();
+    while line.trim().is_empty() {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(false);
+        }
+    }
+
+    let line = line.trim_start_matches('\u{feff}').trim();
+    Ok(line.starts_with('{') && serde_json::from_str::<serde_json::Value>(line).is_ok())
+}
+
+fn first_char_of(sample: &[u8]) -> Option<char> {
+    String::from_utf8_lossy(sample)
+        .trim_start_matches('\u{feff}')
+        .chars()
+        .find(|c| !c.is_whitespace())
+}
+
+/// Replaces struct columns by one column per leaf field, named by its dotted
+/// path (`address.city`).
+fn flatten_structs(df: DataFrame) -> PolarsResult<DataFrame> {
+    if !df
+        .get_columns()
+        .iter()
+        .any(|s| matches!(s.dtype(), DType::Struct(_)))
+    {
+        return Ok(df);
+    }
+
+    let mut columns: Vec<Series> = vec![];
+    for s in df.get_columns() {
+        flatten_series(s, s.name(), &mut columns)?;
+    }
 
-            Ok(TableStruct { columns, rows })
+    // ! A flattened path may clash with a column named with dots already
+    let names = columns
+        .iter()
+        .map(|s| s.name().to_string())
+        .collect::<Vec<String>>();
+    for (s, name) in columns.iter_mut().zip(unique_column_names(&names)) {
+        s.rename(&name);
+    }
+    DataFrame::new(columns)
+}
+
+fn flatten_series(s: &Series, name: &str, columns: &mut Vec<Series>) -> PolarsResult<()> {
+    match s.dtype() {
+        DType::Struct(_) => {
+            for field in s.struct_()?.fields() {
+                flatten_series(field, &format!("{}.{}", name, field.name()), columns)?;
+            }
+        }
+        _ => {
+            let mut s = s.clone();
+            s.rename(name);
+            columns.push(s);
         }
-        Err(err) => Err(err.to_string()),
+    }
+    Ok(())
+}
+
+fn collect_lazy(lf: LazyFrame) -> Result<TableStruct, DavError> {
+    Ok(TableStruct::from_lazy(lf)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cells of the `people` fixtures, row by row under `name`, `age`, `city`
+    const PEOPLE: [[Option<&str>; 3]; 4] = [
+        [Some("Ada"), Some("36"), Some("London")],
+        [Some("Linus"), None, Some("Helsinki")],
+        [Some("Grace"), Some("85"), Some("New York")],
+        [Some("Alan"), Some("41"), Some("Manchester")],
+    ];
+
+    fn fixture(name: &str) -> String {
+        format!("{}/tests/fixtures/{}", env!("CARGO_MANIFEST_DIR"), name)
+    }
+
+    fn load(name: &str) -> TableStruct {
+        load_data_from_file(fixture(name), None, &ImportOptions::default(), |_, _| true)
+            .unwrap_or_else(|err| panic!("{} failed to load: {}", name, err))
+    }
+
+    fn assert_grid(table: &TableStruct, expected: &[[Option<&str>; 3]]) {
+        let names = table
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names, ["name", "age", "city"]);
+        assert_eq!(table.height(), expected.len());
+
+        for (i, row) in expected.iter().enumerate() {
+            let row = row
+                .iter()
+                .map(|cell| cell.map(str::to_string))
+                .collect::<Vec<Option<String>>>();
+            assert_eq!(table.row_texts(i), row, "row {}", i);
+        }
+    }
+
+    #[test]
+    fn csv_rows_line_up_with_columns() {
+        assert_grid(&load("people.csv"), &PEOPLE);
+    }
+
+    #[test]
+    fn parquet_rows_line_up_with_columns() {
+        assert_grid(&load("people.parquet"), &PEOPLE);
+    }
+
+    #[test]
+    fn json_rows_line_up_with_columns() {
+        assert_grid(&load("people.json"), &PEOPLE);
+        assert_grid(&load("people_lines.json"), &PEOPLE);
+    }
+
+    #[test]
+    fn pretty_printed_object_is_not_json_lines() {
+        assert_grid(&load("person.json"), &PEOPLE[..1]);
+    }
+
+    #[test]
+    fn same_named_entries_extract_apart() {
+        let archive = fixture("same_names.zip");
+        assert_eq!(
+            archive_entries(&archive).unwrap(),
+            ["2022/data.csv", "2023/data.csv"]
+        );
+
+        let first = extract_entry(&archive, "2022/data.csv").unwrap();
+        let second = extract_entry(&archive, "2023/data.csv").unwrap();
+        assert_ne!(first, second);
+        assert_eq!(fs::read_to_string(&first).unwrap(), "year\n2022\n");
+        assert_eq!(fs::read_to_string(&second).unwrap(), "year\n2023\n");
+
+        remove_cached(&first);
+        assert!(!Path::new(&first).exists());
+        assert!(Path::new(&second).exists());
+        remove_cached(&second);
+        assert!(!Path::new(&second).parent().unwrap().exists());
+    }
+
+    #[test]
+    fn scanned_files_only_read_the_rows_asked_for() {
+        let options = ImportOptions::default();
+        for name in ["people.csv", "people.parquet"] {
+            let source = scan_file(&fixture(name), &options).unwrap().unwrap();
+            let table = TableStruct::new(source.limit(2).collect().unwrap());
+            assert_grid(&table, &PEOPLE[..2]);
+        }
+        let workbook = scan_file(&fixture("people.xlsx"), &options).unwrap();
+        assert!(workbook.is_none());
+    }
+
+    #[test]
+    fn header_with_a_blank_cell_is_still_the_header() {
+        let table = load("index_column.xlsx");
+        let names = table
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect::<Vec<&str>>();
+        assert_eq!(names[1..], ["name", "age"]);
+        assert_eq!(table.height(), 3);
+        assert_eq!(table.cell_text(0, 1).as_deref(), Some("Ada"));
+    }
+
+    #[test]
+    fn delimiter_ties_go_to_the_preferred_one() {
+        assert_eq!(sniff_delimiter("a,b|c\n1,2|3\n", None, None), Some(b','));
+        assert_eq!(sniff_delimiter("a;b\tc\n1;2\t3\n", None, None), Some(b';'));
+        assert_eq!(sniff_delimiter("a;b;c\n1;2;3\n", None, None), Some(b';'));
+    }
+
+    #[test]
+    fn decompression_reports_progress_and_can_be_cancelled() {
+        // ! Noise does not compress, so the file spans several reports
+        let file_path = env::temp_dir()
+            .join("dav_noise.csv.gz")
+            .to_string_lossy()
+            .to_string();
+        let mut encoder = flate2::write::GzEncoder::new(
+            File::create(&file_path).unwrap(),
+            flate2::Compression::fast(),
+        );
+        let mut state = 1u64;
+        for _ in 0..3 * PROGRESS_BYTES {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+            encoder.write_all(&[(state >> 56) as u8]).unwrap();
+        }
+        encoder.finish().unwrap();
+
+        let cancelled = decompress_file(&file_path, |_| false);
+        assert!(matches!(cancelled, Err(DavError::Cancelled)));
+        assert_eq!(fs::read_dir(cache_dir_of(&file_path)).unwrap().count(), 0);
+
+        let mut reports = vec![];
+        let copy = decompress_file(&file_path, |progress| {
+            reports.push(progress);
+            true
+        })
+        .unwrap();
+        assert!(reports.len() >= 2);
+        assert!(reports.windows(2).all(|w| w[0].bytes < w[1].bytes));
+        assert_eq!(fs::metadata(&copy).unwrap().len(), 3 * PROGRESS_BYTES);
+
+        remove_cached(&file_path);
+        assert!(!cache_dir_of(&file_path).exists());
+        fs::remove_file(&file_path).unwrap();
+    }
+
+    /// Workbooks store every number as a float
+    const PEOPLE_SHEET: [[Option<&str>; 3]; 4] = [
+        [Some("Ada"), Some("36.0"), Some("London")],
+        [Some("Linus"), None, Some("Helsinki")],
+        [Some("Grace"), Some("85.0"), Some("New York")],
+        [Some("Alan"), Some("41.0"), Some("Manchester")],
+    ];
+
+    #[test]
+    fn every_workbook_kind_loads() {
+        for (name, kind) in [
+            ("people.xlsx", WorkbookKind::Xlsx),
+            ("people.xlsb", WorkbookKind::Xlsb),
+            ("people.xls", WorkbookKind::Xls),
+            ("people.ods", WorkbookKind::Ods),
+        ] {
+            assert_eq!(
+                detect_format(&fixture(name)).unwrap(),
+                FileFormat::Workbook(kind),
+                "{}",
+                name
+            );
+            assert_eq!(list_sheets(&fixture(name)).unwrap(), ["People"], "{}", name);
+            assert_grid(&load(name), &PEOPLE_SHEET);
+        }
+    }
+
+    #[test]
+    fn error_cells_keep_the_column_numeric() {
+        let mut table = load("errors.xlsx");
+        assert_eq!(table.columns[1].dtype, DType::Float64);
+        assert_eq!(table.cell_text(1, 1), None);
+        assert_eq!(table.cell_error(1, 1), Some("#DIV/0!"));
+        assert_eq!(table.cell_error(3, 1), Some("#N/A"));
+        assert_eq!(table.cell_error(0, 1), None);
+
+        // ! Errors follow their rows through a sort
+        let df = table
+            .view_source()
+            .sort("name", Default::default())
+            .collect()
+            .unwrap();
+        table.set_view(df);
+        assert_eq!(table.columns.len(), table.df.width());
+        assert_eq!(table.cell_text(1, 0).as_deref(), Some("Alan"));
+        assert_eq!(table.cell_error(1, 1), Some("#N/A"));
+        assert_eq!(table.cell_error(3, 1), Some("#DIV/0!"));
+        assert_eq!(table.cell_error(2, 1), None);
     }
 }
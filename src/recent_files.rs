@@ -0,0 +1,75 @@
+use std::path::Path;
+
+use egui::{Button, RichText, Ui};
+
+use crate::{app::AppModel, read_file::format_icon};
+
+enum RecentAction {
+    Open(String),
+    TogglePin(String),
+    Remove(String),
+}
+
+/// Lists the recent files, pinned first, each with a pin and a remove
+/// button. Returns `true` once a file was opened.
+pub fn render_recent_files(ui: &mut Ui, app: &mut AppModel) -> bool {
+    let recent_files = app.recent_files();
+    if recent_files.is_empty() {
+        ui.label(RichText::new("No recent files").weak());
+        return false;
+    }
+
+    let mut action: Option<RecentAction> = None;
+    for file in recent_files.iter() {
+        ui.horizontal(|ui| {
+            let name = file
+                .file_path
+                .rsplit('/')
+                .next()
+                .unwrap_or(file.file_path.as_str());
+            if ui
+                .add(Button::new(format!("{} {}", format_icon(&file.file_path), name)).frame(false))
+                .on_hover_text(&file.file_path)
+                .clicked()
+            {
+                action = Some(RecentAction::Open(file.file_path.clone()));
+            }
+
+            let (pin_icon, pin_hint) = if file.pinned {
+                (egui_phosphor::regular::PUSH_PIN_SLASH, "Unpin")
+            } else {
+                (egui_phosphor::regular::PUSH_PIN, "Pin")
+            };
+            if ui.small_button(pin_icon).on_hover_text(pin_hint).clicked() {
+                action = Some(RecentAction::TogglePin(file.file_path.clone()));
+            }
+            if ui
+                .small_button(egui_phosphor::regular::X)
+                .on_hover_text("Remove from the list")
+                .clicked()
+            {
+                action = Some(RecentAction::Remove(file.file_path.clone()));
+            }
+        });
+    }
+
+    match action {
+        Some(RecentAction::Open(file_path)) => {
+            // ! Moved or deleted since, it leaves the list and the error is shown
+            if !Path::new(&file_path).exists() {
+                app.remove_recent(&file_path);
+            }
+            app.open_file(file_path);
+            true
+        }
+        Some(RecentAction::TogglePin(file_path)) => {
+            app.toggle_recent_pin(&file_path);
+            false
+        }
+        Some(RecentAction::Remove(file_path)) => {
+            app.remove_recent(&file_path);
+            false
+        }
+        None => false,
+    }
+}
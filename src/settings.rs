@@ -0,0 +1,126 @@
+use std::{collections::BTreeMap, path::Path};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    enums::ThemeMode,
+    models::ImportOptions,
+    utils::{config_dir, load_json, save_json},
+};
+
+const SETTINGS_FILE: &str = "settings.json";
+/// Unpinned entries of the recent files list
+const RECENT_LEN: usize = 10;
+
+/// Everything restored on the next launch.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub theme: ThemeMode,
+    /// Zoom relative to the scale of the display, `None` follows the display
+    pub zoom: Option<f32>,
+    pub window_size: Option<[f32; 2]>,
+    /// Import options last confirmed for each file
+    pub import_options: BTreeMap<String, ImportOptions>,
+    /// Sheet of a workbook, or table of a database, last viewed in each file
+    pub last_sheets: BTreeMap<String, String>,
+    /// Files open when the window closed, in tab order
+    pub session: Vec<SessionFile>,
+    /// Path of the tab that was shown
+    pub active_file: Option<String>,
+    /// Most recently opened first
+    pub recent_files: Vec<RecentFile>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecentFile {
+    pub file_path: String,
+    /// Pinned files stay at the top and are never pushed out
+    pub pinned: bool,
+}
+
+/// An open file of the previous session.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionFile {
+    pub file_path: String,
+    pub sheet: Option<String>,
+    /// Horizontal and vertical scroll offset of its data table
+    pub scroll: [f32; 2],
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Settings {
+            theme: ThemeMode::Dark,
+            zoom: None,
+            window_size: None,
+            import_options: BTreeMap::new(),
+            last_sheets: BTreeMap::new(),
+            session: vec![],
+            active_file: None,
+            recent_files: vec![],
+        }
+    }
+}
+
+impl Settings {
+    /// Saved settings, or the defaults when there are none or they are
+    /// unreadable. Recent files that no longer exist are dropped.
+    pub fn load() -> Self {
+        let mut settings = load_json::<Settings>(config_dir(), SETTINGS_FILE).unwrap_or_default();
+        settings
+            .recent_files
+            .retain(|file| Path::new(&file.file_path).exists());
+        settings
+    }
+
+    /// Recent files to list, pinned ones first.
+    pub fn recent_files(&self) -> Vec<RecentFile> {
+        let mut files = self.recent_files.clone();
+        files.sort_by_key(|file| !file.pinned);
+        files
+    }
+
+    /// Moves a file to the top of the recent files, keeping its pin.
+    pub fn push_recent(&mut self, file_path: &str) {
+        let pinned = self
+            .remove_recent(file_path)
+            .map_or(false, |file| file.pinned);
+        self.recent_files.insert(
+            0,
+            RecentFile {
+                file_path: file_path.to_string(),
+                pinned,
+            },
+        );
+
+        // ! Drop the oldest unpinned files
+        let mut unpinned = 0;
+        self.recent_files.retain(|file| {
+            unpinned += usize::from(!file.pinned);
+            file.pinned || unpinned <= RECENT_LEN
+        });
+    }
+
+    pub fn toggle_pin(&mut self, file_path: &str) {
+        if let Some(file) = self
+            .recent_files
+            .iter_mut()
+            .find(|file| file.file_path == file_path)
+        {
+            file.pinned = !file.pinned;
+        }
+    }
+
+    pub fn remove_recent(&mut self, file_path: &str) -> Option<RecentFile> {
+        let idx = self
+            .recent_files
+            .iter()
+            .position(|file| file.file_path == file_path)?;
+        Some(self.recent_files.remove(idx))
+    }
+
+    pub fn save(&self) {
+        save_json(config_dir(), SETTINGS_FILE, self, "settings");
+    }
+}
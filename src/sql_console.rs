@@ -0,0 +1,140 @@
+use egui::{Button, Color32, Key, RichText, TextEdit, TextStyle, TopBottomPanel};
+
+use crate::utils::{data_dir, load_json, save_json};
+
+const HISTORY_FILE: &str = "query_history.json";
+const HISTORY_LEN: usize = 50;
+
+#[derive(Debug, Clone)]
+pub struct SqlConsole {
+    pub query: String,
+    pub error: Option<String>,
+    pub is_running: bool,
+    history: Vec<String>,
+}
+
+impl Default for SqlConsole {
+    fn default() -> Self {
+        SqlConsole {
+            query: "SELECT * FROM self LIMIT 100".to_owned(),
+            error: None,
+            is_running: false,
+            history: load_history(),
+        }
+    }
+}
+
+impl SqlConsole {
+    /// Renders the console docked at the bottom of the window and returns the
+    /// query to run when the user submits it. `is_database` when the query
+    /// runs in SQLite instead of on the opened file.
+    pub fn show(&mut self, ctx: &egui::Context, is_database: bool) -> Option<String> {
+        let mut submitted = None;
+
+        TopBottomPanel::bottom("sql_console")
+            .resizable(true)
+            .min_height(120.)
+            .show(ctx, |ui| {
+                ui.add_space(5.);
+                ui.horizontal(|ui| {
+                    ui.label(RichText::new("SQL").strong());
+                    ui.label(
+                        RichText::new(if is_database {
+                            "query the tables of the database"
+                        } else {
+                            "query the opened file as `self`"
+                        })
+                        .weak(),
+                    );
+
+                    ui.with_layout(egui::Layout::right_to_left(egui::Align::Center), |ui| {
+                        let run_btn = Button::new(format!("{} Run", egui_phosphor::regular::PLAY))
+                            .shortcut_text(if cfg!(target_os = "macos") {
+                                "⌘⏎"
+                            } else {
+                                "Ctrl+Enter"
+                            });
+                        if ui.add_enabled(!self.is_running, run_btn).clicked() {
+                            submitted = Some(self.query.clone());
+                        }
+
+                        // ! History
+                        ui.add_enabled_ui(!self.history.is_empty(), |ui| {
+                            ui.menu_button(
+                                format!(
+                                    "{} History",
+                                    egui_phosphor::regular::CLOCK_COUNTER_CLOCKWISE
+                                ),
+                                |ui| {
+                                    for query in self.history.iter().rev() {
+                                        let label = query.lines().next().unwrap_or_default();
+                                        if ui.button(label).on_hover_text(query).clicked() {
+                                            self.query = query.clone();
+                                            ui.close_menu();
+                                        }
+                                    }
+                                },
+                            );
+                        });
+
+                        if self.is_running {
+                            ui.spinner();
+                        }
+                    });
+                });
+
+                // ! Syntax/execution errors stay next to the query
+                if let Some(err) = &self.error {
+                    ui.label(
+                        RichText::new(format!("{} {}", egui_phosphor::regular::WARNING, err))
+                            .color(Color32::from_rgb(231, 76, 60)),
+                    );
+                }
+
+                egui::ScrollArea::vertical().show(ui, |ui| {
+                    let response = ui.add(
+                        TextEdit::multiline(&mut self.query)
+                            .code_editor()
+                            .font(TextStyle::Monospace)
+                            .desired_rows(4)
+                            .desired_width(f32::INFINITY),
+                    );
+
+                    if response.has_focus()
+                        && ui.input(|i| i.modifiers.command && i.key_pressed(Key::Enter))
+                        && !self.is_running
+                    {
+                        submitted = Some(self.query.clone());
+                    }
+                });
+            });
+
+        let query = submitted?.trim().to_owned();
+        if query.is_empty() {
+            return None;
+        }
+
+        self.error = None;
+        self.is_running = true;
+        self.push_history(query.clone());
+        Some(query)
+    }
+
+    fn push_history(&mut self, query: String) {
+        self.history.retain(|q| *q != query);
+        self.history.push(query);
+        if self.history.len() > HISTORY_LEN {
+            let overflow = self.history.len() - HISTORY_LEN;
+            self.history.drain(..overflow);
+        }
+        save_history(&self.history);
+    }
+}
+
+fn load_history() -> Vec<String> {
+    load_json(data_dir(), HISTORY_FILE).unwrap_or_default()
+}
+
+fn save_history(history: &[String]) {
+    save_json(data_dir(), HISTORY_FILE, history, "query history");
+}
@@ -0,0 +1,74 @@
+use egui::{Button, Context, RichText, TopBottomPanel};
+
+use crate::{app::AppModel, enums::JobKind, jobs::JobId};
+
+pub fn render_status_bar(app: &mut AppModel, ctx: &Context) {
+    TopBottomPanel::bottom("status_bar")
+        .exact_height(22.)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.horizontal_centered(|ui| {
+                let jobs = app.jobs.jobs();
+                if jobs.is_empty() {
+                    ui.label(RichText::new("Ready").weak());
+                    return;
+                }
+
+                ui.spinner();
+
+                // ! Running jobs popover
+                let mut cancelled: Vec<JobId> = vec![];
+                ui.menu_button(
+                    format!(
+                        "{} job{} running",
+                        jobs.len(),
+                        if jobs.len() > 1 { "s" } else { "" }
+                    ),
+                    |ui| {
+                        for job in jobs {
+                            ui.horizontal(|ui| {
+                                let icon = match job.kind {
+                                    JobKind::Load => egui_phosphor::regular::SPINNER,
+                                    JobKind::View => egui_phosphor::regular::FUNNEL,
+                                    JobKind::Query => egui_phosphor::regular::FILE_SQL,
+                                    JobKind::Preview => egui_phosphor::regular::EYE,
+                                    JobKind::Export => egui_phosphor::regular::EXPORT,
+                                };
+                                let document = app
+                                    .documents
+                                    .iter()
+                                    .find(|d| d.id == job.doc_id)
+                                    .map(|d| d.title())
+                                    .unwrap_or_default();
+
+                                ui.label(format!("{} {}", icon, job.label));
+                                ui.label(RichText::new(document).weak());
+                                if let Some(fraction) = job.progress {
+                                    ui.label(format!("{:.0}%", fraction * 100.));
+                                }
+                                ui.label(
+                                    RichText::new(format!(
+                                        "{:.1}s",
+                                        job.started_at.elapsed().as_secs_f32()
+                                    ))
+                                    .weak(),
+                                );
+
+                                if ui
+                                    .add(Button::new(egui_phosphor::regular::X).small())
+                                    .on_hover_text("Cancel")
+                                    .clicked()
+                                {
+                                    cancelled.push(job.id);
+                                }
+                            });
+                        }
+                    },
+                );
+
+                for job_id in cancelled {
+                    app.jobs.cancel(job_id);
+                }
+            });
+        });
+}
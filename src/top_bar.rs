@@ -1,6 +1,9 @@
-use egui::{menu, Button, Context, TopBottomPanel, Visuals};
+use egui::{menu, Button, Context, ScrollArea, TopBottomPanel};
 
-use crate::{app::AppModel, enums::ThemeMode, utils::thrd_select_file};
+use crate::{
+    app::AppModel, enums::ThemeMode, read_file::has_import_options,
+    recent_files::render_recent_files, utils::thrd_select_file,
+};
 
 pub fn render_top_bar(app: &mut AppModel, ctx: &Context, frame: &mut eframe::Frame) {
     TopBottomPanel::top("top_bar")
@@ -26,10 +29,17 @@ pub fn render_top_bar(app: &mut AppModel, ctx: &Context, frame: &mut eframe::Fra
                         ui.close_menu();
                     }
 
+                    // ! Open recent
+                    ui.menu_button("Open recent", |ui| {
+                        if render_recent_files(ui, app) {
+                            ui.close_menu();
+                        }
+                    });
+
                     // ! Close file
                     if ui
                         .add_enabled(
-                            app.file_path.is_some(),
+                            app.active_document.is_some(),
                             Button::new("Close file").shortcut_text({
                                 if cfg!(target_os = "macos") {
                                     "⌘W"
@@ -40,8 +50,31 @@ pub fn render_top_bar(app: &mut AppModel, ctx: &Context, frame: &mut eframe::Fra
                         )
                         .clicked()
                     {
-                        app.file_path = None;
-                        app.table_struct = None;
+                        app.close_active_document();
+                        ui.close_menu();
+                    }
+
+                    // ! Import options
+                    let can_import = app
+                        .active_document()
+                        .map_or(false, |doc| has_import_options(&doc.file_path));
+                    if ui
+                        .add_enabled(can_import, Button::new("Import options..."))
+                        .clicked()
+                    {
+                        app.open_import_dialog();
+                        ui.close_menu();
+                    }
+
+                    // ! Export
+                    let can_export = app
+                        .active_document()
+                        .map_or(false, |doc| doc.data.is_some());
+                    if ui
+                        .add_enabled(can_export, Button::new("Export view..."))
+                        .clicked()
+                    {
+                        app.open_export_dialog();
                         ui.close_menu();
                     }
 
@@ -124,6 +157,32 @@ pub fn render_top_bar(app: &mut AppModel, ctx: &Context, frame: &mut eframe::Fra
 
                 // ! Tools
                 ui.menu_button("Tools", |ui| {
+                    // ! SQL console
+                    if ui
+                        .add_enabled(
+                            app.active_document()
+                                .map_or(false, |doc| doc.data.is_some()),
+                            Button::new({
+                                if app.is_sql_console_open {
+                                    format!("{} SQL console", egui_phosphor::regular::CHECK)
+                                } else {
+                                    "SQL console".to_owned()
+                                }
+                            })
+                            .shortcut_text({
+                                if cfg!(target_os = "macos") {
+                                    "⌘E"
+                                } else {
+                                    "Ctrl+E"
+                                }
+                            }),
+                        )
+                        .clicked()
+                    {
+                        app.is_sql_console_open = !app.is_sql_console_open;
+                        ui.close_menu();
+                    }
+
                     ui.menu_button("Theme", |ui| {
                         // LIGHT
                         if ui
@@ -137,7 +196,7 @@ pub fn render_top_bar(app: &mut AppModel, ctx: &Context, frame: &mut eframe::Fra
                             .clicked()
                         {
                             app.selected_theme = ThemeMode::Light;
-                            ui.ctx().set_visuals(Visuals::light());
+                            app.selected_theme.apply(ui.ctx());
                             ui.close_menu();
                         }
 
@@ -151,7 +210,7 @@ pub fn render_top_bar(app: &mut AppModel, ctx: &Context, frame: &mut eframe::Fra
                             .clicked()
                         {
                             app.selected_theme = ThemeMode::Dark;
-                            ui.ctx().set_visuals(Visuals::dark());
+                            app.selected_theme.apply(ui.ctx());
                             ui.close_menu();
                         }
 
@@ -165,7 +224,7 @@ pub fn render_top_bar(app: &mut AppModel, ctx: &Context, frame: &mut eframe::Fra
                             .clicked()
                         {
                             app.selected_theme = ThemeMode::Frappe;
-                            catppuccin_egui::set_theme(ui.ctx(), catppuccin_egui::FRAPPE);
+                            app.selected_theme.apply(ui.ctx());
                             ui.close_menu();
                         }
 
@@ -179,7 +238,7 @@ pub fn render_top_bar(app: &mut AppModel, ctx: &Context, frame: &mut eframe::Fra
                             .clicked()
                         {
                             app.selected_theme = ThemeMode::Latte;
-                            catppuccin_egui::set_theme(ui.ctx(), catppuccin_egui::LATTE);
+                            app.selected_theme.apply(ui.ctx());
                             ui.close_menu();
                         }
 
@@ -193,7 +252,7 @@ pub fn render_top_bar(app: &mut AppModel, ctx: &Context, frame: &mut eframe::Fra
                             .clicked()
                         {
                             app.selected_theme = ThemeMode::Macchiato;
-                            catppuccin_egui::set_theme(ui.ctx(), catppuccin_egui::MACCHIATO);
+                            app.selected_theme.apply(ui.ctx());
                             ui.close_menu();
                         }
 
@@ -207,7 +266,7 @@ pub fn render_top_bar(app: &mut AppModel, ctx: &Context, frame: &mut eframe::Fra
                             .clicked()
                         {
                             app.selected_theme = ThemeMode::Mocha;
-                            catppuccin_egui::set_theme(ui.ctx(), catppuccin_egui::MOCHA);
+                            app.selected_theme.apply(ui.ctx());
                             ui.close_menu();
                         }
                     });
@@ -224,3 +283,43 @@ pub fn render_top_bar(app: &mut AppModel, ctx: &Context, frame: &mut eframe::Fra
             });
         });
 }
+
+pub fn render_document_tabs(app: &mut AppModel, ctx: &Context) {
+    TopBottomPanel::top("document_tabs")
+        .resizable(false)
+        .show_separator_line(true)
+        .show(ctx, |ui| {
+            ScrollArea::horizontal()
+                .auto_shrink([false, true])
+                .show(ui, |ui| {
+                    ui.horizontal(|ui| {
+                        let mut to_close = None;
+
+                        for doc in app.documents.iter() {
+                            let is_active = app.active_document == Some(doc.id);
+                            if ui
+                                .selectable_label(is_active, doc.tab_label())
+                                .on_hover_text(&doc.file_path)
+                                .clicked()
+                            {
+                                app.active_document = Some(doc.id);
+                            }
+
+                            if ui
+                                .small_button(egui_phosphor::regular::X)
+                                .on_hover_text("Close tab")
+                                .clicked()
+                            {
+                                to_close = Some(doc.id);
+                            }
+
+                            ui.separator();
+                        }
+
+                        if let Some(id) = to_close {
+                            app.close_document(id);
+                        }
+                    });
+                });
+        });
+}
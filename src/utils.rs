@@ -1,9 +1,37 @@
-use std::{path::Path, sync::mpsc::Sender};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::Sender,
+        Arc,
+    },
+};
 
 use egui::{Key, Ui};
+use polars::{
+    prelude::{DataFrame, LazyFrame},
+    sql::SQLContext,
+};
 use rfd::AsyncFileDialog;
+use serde::{de::DeserializeOwned, Serialize};
 
-use crate::{app::AppModel, enums::ChannelMessage, read_file::load_data_from_file};
+use crate::{
+    app::AppModel,
+    enums::{ChannelMessage, ExportFormat, ParquetCodec},
+    errors::DavError,
+    jobs::JobId,
+    models::{ColumnInfo, ImportOptions, TableStruct, ViewState},
+    read_file::{
+        decompress_file, extract_entry, list_sheets, load_data_from_file, preview_file,
+        query_sqlite, COMPRESSED_EXTENSIONS, IPC_EXTENSIONS, JSON_EXTENSIONS, SQLITE_EXTENSIONS,
+        TEXT_EXTENSIONS, WORKBOOK_EXTENSIONS,
+    },
+    write_file::export_dataframe,
+};
+
+/// Rows shown by the import dialog preview
+const PREVIEW_ROWS: usize = 20;
 
 pub fn handle_keyboard_shortcuts(ui: &Ui, app: &mut AppModel, frame: &mut eframe::Frame) {
     let ctx = ui.ctx();
@@ -13,10 +41,14 @@ pub fn handle_keyboard_shortcuts(ui: &Ui, app: &mut AppModel, frame: &mut eframe
         thrd_select_file(app.tx.clone());
     }
 
-    // ! Close file shortcut
+    // ! Close tab shortcut
     if ctx.input(|i| i.modifiers.command && i.key_pressed(Key::W)) {
-        app.file_path = None;
-        app.table_struct = None;
+        app.close_active_document();
+    }
+
+    // ! SQL console shortcut
+    if ctx.input(|i| i.modifiers.command && i.key_pressed(Key::E)) {
+        app.is_sql_console_open = !app.is_sql_console_open;
     }
 
     // ! Quit shortcut
@@ -50,46 +82,312 @@ pub fn thrd_select_file(tx: Sender<ChannelMessage>) {
         let user_dirs = directories::UserDirs::new().unwrap();
         let desktop_dir: &Path = user_dirs.desktop_dir().unwrap();
 
+        let mut all = vec!["parquet", "avro"];
+        all.extend(IPC_EXTENSIONS);
+        all.extend(TEXT_EXTENSIONS);
+        all.extend(JSON_EXTENSIONS);
+        all.extend(WORKBOOK_EXTENSIONS);
+        all.extend(SQLITE_EXTENSIONS);
+        all.extend(COMPRESSED_EXTENSIONS);
+        all.push("zip");
+
+        let mut compressed = COMPRESSED_EXTENSIONS.to_vec();
+        compressed.push("zip");
+
         let res = AsyncFileDialog::new()
-            .add_filter("All", &["csv", "parquet", "xlsx"])
-            .add_filter("csv", &["csv"])
+            .add_filter("All", &all)
+            .add_filter("Text", &TEXT_EXTENSIONS)
             .add_filter("parquet", &["parquet"])
-            // .add_filter("json", &["json"])
-            .add_filter("xlsx", &["xlsx"])
+            .add_filter("Arrow IPC", &IPC_EXTENSIONS)
+            .add_filter("Avro", &["avro"])
+            .add_filter("JSON", &JSON_EXTENSIONS)
+            .add_filter("Excel", &["xlsx", "xlsm", "xls", "xlsb"])
+            .add_filter("OpenDocument", &["ods"])
+            .add_filter("SQLite", &SQLITE_EXTENSIONS)
+            .add_filter("Compressed", &compressed)
             .set_directory(desktop_dir)
-            .pick_file()
+            .pick_files()
             .await;
 
-        if let Some(file_path) = res {
+        // ! Every picked file opens in its own tab
+        for file_path in res.unwrap_or_default() {
             let path = file_path
                 .path()
                 .to_str()
                 .unwrap()
                 .to_owned()
                 .replace('\\', "/");
-            tx.send(ChannelMessage::OpenFile(path.clone())).unwrap();
-
-            // ! Read file
-            read_file(tx, path);
+            tx.send(ChannelMessage::OpenFile(path)).unwrap();
         }
     });
 }
 
-pub fn thrd_read_file(tx: Sender<ChannelMessage>, file_path: String) {
+/// Reads `file_path`, or one `sheet` of it when it is a workbook.
+pub fn thrd_read_file(
+    tx: Sender<ChannelMessage>,
+    job_id: JobId,
+    file_path: String,
+    sheet: Option<String>,
+    options: ImportOptions,
+    cancel: Arc<AtomicBool>,
+) {
     println!("[*] Reading file at {}...", file_path.clone());
     tokio::spawn(async move {
-        read_file(tx, file_path);
+        read_file(tx, job_id, file_path, sheet, options, cancel);
     });
 }
 
-fn read_file(tx: Sender<ChannelMessage>, file_path: String) {
-    let table_struct = load_data_from_file(file_path);
+fn read_file(
+    tx: Sender<ChannelMessage>,
+    job_id: JobId,
+    file_path: String,
+    sheet: Option<String>,
+    options: ImportOptions,
+    cancel: Arc<AtomicBool>,
+) {
+    // ! A compressed file is decompressed to the cache first, reporting how
+    // ! far it got since big ones take a while
+    let decompressed = decompress_file(&file_path, |progress| {
+        !cancel.load(Ordering::Relaxed)
+            && tx
+                .send(ChannelMessage::LoadProgress(job_id, progress))
+                .is_ok()
+    });
+    if let Err(err) = decompressed {
+        if !cancel.load(Ordering::Relaxed) {
+            tx.send(ChannelMessage::ReadFileError(job_id, err)).unwrap();
+        }
+        return;
+    }
+
+    // ! Workbooks list their sheets first, the sheet bar shows them while the
+    // ! first one loads
+    let sheet = match (sheet, list_sheets(&file_path)) {
+        (_, Err(err)) => {
+            tx.send(ChannelMessage::ReadFileError(job_id, err)).unwrap();
+            return;
+        }
+        (_, Ok(sheets)) if sheets.is_empty() => None,
+        (sheet, Ok(sheets)) => {
+            let sheet = sheet
+                .filter(|s| sheets.contains(s))
+                .or_else(|| sheets.first().cloned());
+            tx.send(ChannelMessage::SheetNames(job_id, sheets)).unwrap();
+            sheet
+        }
+    };
+
+    let table_struct =
+        load_data_from_file(file_path, sheet.as_deref(), &options, |df, progress| {
+            if cancel.load(Ordering::Relaxed) {
+                return false;
+            }
+
+            // ! The UI may be gone already, nothing to report then
+            tx.send(ChannelMessage::LoadProgress(job_id, progress))
+                .is_ok()
+                && tx
+                    .send(ChannelMessage::LoadBatch(job_id, df.clone()))
+                    .is_ok()
+        });
+
+    // ! The job manager already dropped this load
+    if cancel.load(Ordering::Relaxed) {
+        println!("[*] Reading cancelled");
+        return;
+    }
+
     match table_struct {
         Ok(table_struct) => {
-            tx.send(ChannelMessage::ReadFile(table_struct)).unwrap();
+            tx.send(ChannelMessage::ReadFile(job_id, table_struct))
+                .unwrap();
         }
         Err(err) => {
-            tx.send(ChannelMessage::ReadFileError(err)).unwrap();
+            tx.send(ChannelMessage::ReadFileError(job_id, err)).unwrap();
+        }
+    }
+}
+
+pub fn thrd_preview_file(
+    tx: Sender<ChannelMessage>,
+    job_id: JobId,
+    file_path: String,
+    sheet: Option<String>,
+    options: ImportOptions,
+) {
+    println!("[*] Previewing file at {}...", file_path.clone());
+    tokio::spawn(async move {
+        match preview_file(file_path, sheet.as_deref(), &options, PREVIEW_ROWS) {
+            Ok(df) => {
+                tx.send(ChannelMessage::PreviewResult(job_id, df)).unwrap();
+            }
+            Err(err) => {
+                tx.send(ChannelMessage::PreviewError(job_id, err)).unwrap();
+            }
+        }
+    });
+}
+
+/// Extracts one file of a zip archive, then opens it like a picked file.
+pub fn thrd_extract_entry(tx: Sender<ChannelMessage>, file_path: String, entry: String) {
+    println!("[*] Extracting {} from {}...", entry, file_path);
+    tokio::spawn(async move {
+        match extract_entry(&file_path, &entry) {
+            Ok(path) => {
+                tx.send(ChannelMessage::OpenFile(path)).unwrap();
+            }
+            Err(err) => {
+                tx.send(ChannelMessage::FileError(file_path, err)).unwrap();
+            }
+        }
+    });
+}
+
+/// Polars cannot interrupt a `collect`, a cancelled view still runs to the end
+/// and its result is dropped by the job manager.
+pub fn thrd_apply_view(
+    tx: Sender<ChannelMessage>,
+    job_id: JobId,
+    source: LazyFrame,
+    columns: Vec<ColumnInfo>,
+    view: ViewState,
+) {
+    println!("[*] Applying view...");
+    tokio::spawn(async move {
+        match view.apply(source, &columns).collect() {
+            Ok(df) => {
+                tx.send(ChannelMessage::ViewApplied(job_id, df)).unwrap();
+            }
+            Err(err) => {
+                tx.send(ChannelMessage::ViewError(job_id, err.into()))
+                    .unwrap();
+            }
+        }
+    });
+}
+
+pub fn thrd_run_query(tx: Sender<ChannelMessage>, job_id: JobId, source: LazyFrame, query: String) {
+    println!("[*] Running query...");
+    tokio::spawn(async move {
+        let mut sql_ctx = SQLContext::new();
+        sql_ctx.register("self", source);
+
+        match sql_ctx.execute(&query).and_then(TableStruct::from_lazy) {
+            Ok(table_struct) => {
+                tx.send(ChannelMessage::QueryResult(job_id, table_struct))
+                    .unwrap();
+            }
+            Err(err) => {
+                tx.send(ChannelMessage::QueryError(job_id, err.into()))
+                    .unwrap();
+            }
+        }
+    });
+}
+
+/// Runs a query against the database of a SQLite document.
+pub fn thrd_run_sqlite_query(
+    tx: Sender<ChannelMessage>,
+    job_id: JobId,
+    file_path: String,
+    query: String,
+) {
+    println!("[*] Running query on {}...", file_path);
+    tokio::spawn(async move {
+        match query_sqlite(&file_path, &query) {
+            Ok(df) => {
+                tx.send(ChannelMessage::QueryResult(job_id, TableStruct::new(df)))
+                    .unwrap();
+            }
+            Err(err) => {
+                tx.send(ChannelMessage::QueryError(job_id, err)).unwrap();
+            }
         }
+    });
+}
+
+/// Asks where to save, then writes `df` there. Cancelling the file dialog
+/// cancels the job.
+pub fn thrd_export_file(
+    tx: Sender<ChannelMessage>,
+    job_id: JobId,
+    df: DataFrame,
+    format: ExportFormat,
+    codec: ParquetCodec,
+    file_stem: String,
+    cancel: Arc<AtomicBool>,
+) {
+    println!("[*] Exporting view...");
+    tokio::spawn(async move {
+        let res = AsyncFileDialog::new()
+            .add_filter(format.label(), &[format.extension()])
+            .set_file_name(&format!("{}.{}", file_stem, format.extension()))
+            .save_file()
+            .await;
+        let Some(file_path) = res else {
+            tx.send(ChannelMessage::ExportError(job_id, DavError::Cancelled))
+                .unwrap();
+            return;
+        };
+        let path = file_path.path().to_string_lossy().replace('\\', "/");
+
+        let result = export_dataframe(&df, &path, format, codec, |fraction| {
+            tx.send(ChannelMessage::ExportProgress(job_id, fraction))
+                .unwrap();
+            !cancel.load(Ordering::Relaxed)
+        });
+        match result {
+            Ok(()) => {
+                tx.send(ChannelMessage::ExportFinished(job_id, path))
+                    .unwrap();
+            }
+            Err(err) => {
+                tx.send(ChannelMessage::ExportError(job_id, err)).unwrap();
+            }
+        }
+    });
+}
+
+/// Reads a JSON file of `dir`, `None` when it is missing or unreadable.
+pub fn load_json<T: DeserializeOwned>(dir: Option<PathBuf>, file_name: &str) -> Option<T> {
+    let content = fs::read_to_string(dir?.join(file_name)).ok()?;
+    serde_json::from_str::<T>(&content).ok()
+}
+
+/// Writes `value` as JSON to a file of `dir`, logging why `what` could not be
+/// saved.
+pub fn save_json<T: Serialize + ?Sized>(
+    dir: Option<PathBuf>,
+    file_name: &str,
+    value: &T,
+    what: &str,
+) {
+    let Some(dir) = dir else {
+        return;
+    };
+
+    match serde_json::to_string_pretty(value) {
+        Ok(content) => {
+            if let Err(err) = fs::write(dir.join(file_name), content) {
+                println!("[!] Cannot save {}: {}", what, err);
+            }
+        }
+        Err(err) => println!("[!] Cannot save {}: {}", what, err),
     }
 }
+
+/// Per-user data directory (query history, ...), created on first use.
+pub fn data_dir() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "DaV")?;
+    let dir = dirs.data_dir().to_path_buf();
+    fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
+
+/// Per-user config directory (settings), created on first use.
+pub fn config_dir() -> Option<PathBuf> {
+    let dirs = directories::ProjectDirs::from("", "", "DaV")?;
+    let dir = dirs.config_dir().to_path_buf();
+    fs::create_dir_all(&dir).ok()?;
+    Some(dir)
+}
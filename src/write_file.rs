@@ -0,0 +1,178 @@
+use std::fs::{self, File};
+
+use polars::prelude::{
+    AnyValue, CsvWriter, DataFrame, IpcWriter, JsonFormat, JsonWriter, ParquetCompression,
+    ParquetWriter, SerWriter,
+};
+use rust_xlsxwriter::{Workbook, XlsxError};
+
+use crate::{
+    enums::{ExportFormat, ParquetCodec},
+    errors::DavError,
+};
+
+/// Rows written between two progress reports.
+const EXPORT_BATCH_ROWS: usize = 50_000;
+
+/// Rows of an Excel worksheet, the header included.
+const XLSX_MAX_ROWS: usize = 1_048_576;
+
+/// Writes `df` to `file_path`, calling `on_progress` with the fraction of the
+/// rows written after each batch. Returning `false` from `on_progress`
+/// cancels the export and removes the partial file.
+pub fn export_dataframe(
+    df: &DataFrame,
+    file_path: &str,
+    format: ExportFormat,
+    codec: ParquetCodec,
+    mut on_progress: impl FnMut(f32) -> bool,
+) -> Result<(), DavError> {
+    let result = match format {
+        ExportFormat::Csv => write_csv(df, file_path, &mut on_progress),
+        ExportFormat::Parquet => write_parquet(df, file_path, codec, &mut on_progress),
+        ExportFormat::NdJson => write_ndjson(df, file_path, &mut on_progress),
+        ExportFormat::Ipc => write_ipc(df, file_path, &mut on_progress),
+        ExportFormat::Xlsx => write_xlsx(df, file_path, &mut on_progress),
+    };
+
+    // ! Never leave a truncated file behind
+    if result.is_err() {
+        fs::remove_file(file_path).ok();
+    }
+    result
+}
+
+/// Walks `df` in slices of `EXPORT_BATCH_ROWS`, reporting progress after each.
+fn for_each_batch(
+    df: &DataFrame,
+    on_progress: &mut impl FnMut(f32) -> bool,
+    mut write: impl FnMut(&mut DataFrame, bool) -> Result<(), DavError>,
+) -> Result<(), DavError> {
+    let total_rows = df.height();
+    let mut offset = 0;
+    loop {
+        let mut batch = df.slice(offset as i64, EXPORT_BATCH_ROWS);
+        write(&mut batch, offset == 0)?;
+        offset += EXPORT_BATCH_ROWS;
+
+        if !on_progress((offset.min(total_rows) as f32 / total_rows.max(1) as f32).min(1.)) {
+            return Err(DavError::Cancelled);
+        }
+        if offset >= total_rows {
+            return Ok(());
+        }
+    }
+}
+
+fn write_csv(
+    df: &DataFrame,
+    file_path: &str,
+    on_progress: &mut impl FnMut(f32) -> bool,
+) -> Result<(), DavError> {
+    let mut file = File::create(file_path)?;
+    for_each_batch(df, on_progress, |batch, is_first| {
+        CsvWriter::new(&mut file)
+            .has_header(is_first)
+            .finish(batch)?;
+        Ok(())
+    })
+}
+
+fn write_ndjson(
+    df: &DataFrame,
+    file_path: &str,
+    on_progress: &mut impl FnMut(f32) -> bool,
+) -> Result<(), DavError> {
+    let mut file = File::create(file_path)?;
+    for_each_batch(df, on_progress, |batch, _| {
+        JsonWriter::new(&mut file)
+            .with_json_format(JsonFormat::JsonLines)
+            .finish(batch)?;
+        Ok(())
+    })
+}
+
+fn write_parquet(
+    df: &DataFrame,
+    file_path: &str,
+    codec: ParquetCodec,
+    on_progress: &mut impl FnMut(f32) -> bool,
+) -> Result<(), DavError> {
+    let compression = match codec {
+        ParquetCodec::Uncompressed => ParquetCompression::Uncompressed,
+        ParquetCodec::Snappy => ParquetCompression::Snappy,
+        ParquetCodec::Gzip => ParquetCompression::Gzip(None),
+        ParquetCodec::Lz4 => ParquetCompression::Lz4Raw,
+        ParquetCodec::Zstd => ParquetCompression::Zstd(None),
+    };
+    let mut writer = ParquetWriter::new(File::create(file_path)?)
+        .with_compression(compression)
+        .batched(&df.schema())?;
+    for_each_batch(df, on_progress, |batch, _| Ok(writer.write_batch(batch)?))?;
+    writer.finish()?;
+    Ok(())
+}
+
+fn write_ipc(
+    df: &DataFrame,
+    file_path: &str,
+    on_progress: &mut impl FnMut(f32) -> bool,
+) -> Result<(), DavError> {
+    let mut writer = IpcWriter::new(File::create(file_path)?).batched(&df.schema())?;
+    for_each_batch(df, on_progress, |batch, _| Ok(writer.write_batch(batch)?))?;
+    writer.finish()?;
+    Ok(())
+}
+
+/// Numbers and booleans keep their type, every other value is written as the
+/// text shown in the table.
+fn write_xlsx(
+    df: &DataFrame,
+    file_path: &str,
+    on_progress: &mut impl FnMut(f32) -> bool,
+) -> Result<(), DavError> {
+    if df.height() >= XLSX_MAX_ROWS {
+        return Err(DavError::Parse(format!(
+            "Excel sheets hold at most {} rows, the view has {}",
+            XLSX_MAX_ROWS - 1,
+            df.height()
+        )));
+    }
+
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    for (col, series) in df.get_columns().iter().enumerate() {
+        worksheet
+            .write_string(0, col as u16, series.name())
+            .map_err(xlsx_error)?;
+    }
+
+    let mut offset = 0;
+    for_each_batch(df, on_progress, |batch, _| {
+        for (col, series) in batch.get_columns().iter().enumerate() {
+            let is_numeric = series.dtype().is_numeric();
+            for (idx, value) in series.iter().enumerate() {
+                let (row, col) = ((offset + idx + 1) as u32, col as u16);
+                let written = match value {
+                    AnyValue::Null => continue,
+                    AnyValue::Boolean(v) => worksheet.write_boolean(row, col, v),
+                    value if is_numeric => {
+                        worksheet.write_number(row, col, value.extract::<f64>().unwrap_or_default())
+                    }
+                    AnyValue::Utf8(v) => worksheet.write_string(row, col, v),
+                    value => worksheet.write_string(row, col, value.to_string()),
+                };
+                written.map_err(xlsx_error)?;
+            }
+        }
+        offset += batch.height();
+        Ok(())
+    })?;
+
+    workbook.save(file_path).map_err(xlsx_error)?;
+    Ok(())
+}
+
+fn xlsx_error(err: XlsxError) -> DavError {
+    DavError::Parse(err.to_string())
+}